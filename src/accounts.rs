@@ -0,0 +1,353 @@
+use crate::{
+    transport::{DefaultTransport, Transport},
+    wilma::{Message, Reservation},
+    Client, Credentials, Error as WilmaError, Secret,
+};
+use async_lock::Mutex;
+use chrono::NaiveDate;
+use futures::future::join_all;
+use std::fmt;
+use std::future::Future;
+use std::sync::Arc;
+
+/// Identifies an account: the same login can have several roles, for example a guardian with
+/// multiple children.
+#[derive(Debug, Clone, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub struct AccountKey {
+    pub server: String,
+    pub username: String,
+
+    /// The identity (`!0123456`) or the name of the role. The first role is used if none is set.
+    pub role: Option<String>,
+}
+
+impl fmt::Display for AccountKey {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}@{}", self.username, self.server)?;
+
+        match &self.role {
+            Some(role) => write!(f, " ({})", role),
+            None => Ok(()),
+        }
+    }
+}
+
+struct Account<T: Transport> {
+    key: AccountKey,
+    password: Secret,
+
+    /// The logged in client. The lock is held while logging in, so every account logs in once
+    /// even if it's queried concurrently.
+    client: Mutex<Option<Arc<Client<T>>>>,
+}
+
+type TransportFactory<T> = Box<dyn Fn() -> anyhow::Result<T> + Send + Sync>;
+type Setup<T> = Box<dyn Fn(Client<T>) -> Client<T> + Send + Sync>;
+
+/// Holds the clients of several accounts, possibly on different Wilma servers.
+///
+/// Accounts are logged in lazily when they are first queried, and logged in again when their
+/// session expires. Aggregated queries run concurrently over every account and return a result
+/// per account, so one failing account doesn't affect the others.
+pub struct AccountManager<T: Transport = DefaultTransport> {
+    accounts: Vec<Account<T>>,
+    transport: TransportFactory<T>,
+    setup: Option<Setup<T>>,
+}
+
+#[cfg(feature = "reqwest")]
+impl AccountManager {
+    pub fn new() -> Self {
+        Self::with_transport(DefaultTransport::new)
+    }
+}
+
+#[cfg(feature = "reqwest")]
+impl Default for AccountManager {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<T: Transport> AccountManager<T> {
+    /// Creates the transport of every client with the given function.
+    pub fn with_transport<F>(transport: F) -> Self
+    where
+        F: Fn() -> anyhow::Result<T> + Send + Sync + 'static,
+    {
+        Self {
+            accounts: Vec::new(),
+            transport: Box::new(transport),
+            setup: None,
+        }
+    }
+
+    /// Configures every client after it logs in, for example with `Client::with_cache`.
+    pub fn with_setup<F>(mut self, setup: F) -> Self
+    where
+        F: Fn(Client<T>) -> Client<T> + Send + Sync + 'static,
+    {
+        self.setup = Some(Box::new(setup));
+        self
+    }
+
+    /// Adds an account, replacing an existing one with the same key. The account isn't logged in
+    /// until it's used.
+    pub fn add(&mut self, credentials: Credentials<'_>, role: Option<&str>) -> AccountKey {
+        let key = AccountKey {
+            server: credentials.server.into(),
+            username: credentials.username.into(),
+            role: role.map(String::from),
+        };
+
+        self.remove(&key);
+        self.accounts.push(Account {
+            key: key.clone(),
+            password: credentials.password.into(),
+            client: Mutex::new(None),
+        });
+
+        key
+    }
+
+    /// Removes an account. Returns whether the account existed.
+    pub fn remove(&mut self, key: &AccountKey) -> bool {
+        let count = self.accounts.len();
+        self.accounts.retain(|a| &a.key != key);
+
+        self.accounts.len() != count
+    }
+
+    /// The keys of every account, in the order they were added.
+    pub fn keys(&self) -> Vec<&AccountKey> {
+        self.accounts.iter().map(|a| &a.key).collect()
+    }
+
+    /// The client of an account, logging it in first if needed.
+    pub async fn client(&self, key: &AccountKey) -> anyhow::Result<Arc<Client<T>>> {
+        self.account(key)?.client(self, None).await
+    }
+
+    /// Logs in every account that isn't logged in yet, concurrently.
+    pub async fn login_all(&self) -> Vec<(AccountKey, anyhow::Result<()>)> {
+        self.query(|_| async { Ok(()) }).await
+    }
+
+    /// Runs a query concurrently on every account. An account whose session has expired is
+    /// logged in again and the query is retried once.
+    pub async fn query<F, Fut, R>(&self, query: F) -> Vec<(AccountKey, anyhow::Result<R>)>
+    where
+        F: Fn(Arc<Client<T>>) -> Fut,
+        Fut: Future<Output = anyhow::Result<R>>,
+    {
+        let query = &query;
+
+        join_all(
+            self.accounts.iter().map(|account| async move {
+                (account.key.clone(), account.query(self, query).await)
+            }),
+        )
+        .await
+    }
+
+    /// The reservations of every account on the given date. Holidays and days outside the terms
+    /// have no reservations.
+    pub async fn schedules_on(
+        &self,
+        date: NaiveDate,
+    ) -> Vec<(AccountKey, anyhow::Result<Vec<Reservation>>)> {
+        self.query(|client| async move {
+            let schedule = client.get_user_schedule().await?;
+            let calendar = client.get_calendar(date, date).await?;
+
+            Ok(schedule
+                .expand(date, date, &calendar)
+                .into_iter()
+                .map(|lesson| lesson.reservation.clone())
+                .collect())
+        })
+        .await
+    }
+
+    /// The unread messages of every account.
+    pub async fn unread_messages(&self) -> Vec<(AccountKey, anyhow::Result<Vec<Message>>)> {
+        self.query(|client| async move {
+            let messages = client.get_messages().await?;
+
+            Ok(messages.into_iter().filter(|m| m.is_unread()).collect())
+        })
+        .await
+    }
+
+    fn account(&self, key: &AccountKey) -> anyhow::Result<&Account<T>> {
+        self.accounts
+            .iter()
+            .find(|a| &a.key == key)
+            .ok_or_else(|| anyhow::anyhow!("No account {} in the manager.", key))
+    }
+}
+
+impl<T: Transport> Account<T> {
+    /// Returns the logged in client. If `expired` is the current client, it's replaced with a
+    /// new login; otherwise another query already did that.
+    async fn client(
+        &self,
+        manager: &AccountManager<T>,
+        expired: Option<&Arc<Client<T>>>,
+    ) -> anyhow::Result<Arc<Client<T>>> {
+        let mut client = self.client.lock().await;
+
+        match (&*client, expired) {
+            (Some(current), Some(expired)) if !Arc::ptr_eq(current, expired) => {
+                return Ok(current.clone())
+            }
+            (Some(current), None) => return Ok(current.clone()),
+            _ => {}
+        }
+
+        let credentials = Credentials {
+            username: &self.key.username,
+            password: self.password.expose(),
+            server: &self.key.server,
+        };
+
+        let mut logged_in = Client::login_as(
+            (manager.transport)()?,
+            credentials,
+            self.key.role.as_deref(),
+        )
+        .await?;
+
+        if let Some(setup) = &manager.setup {
+            logged_in = setup(logged_in);
+        }
+
+        let logged_in = Arc::new(logged_in);
+        *client = Some(logged_in.clone());
+
+        Ok(logged_in)
+    }
+
+    async fn query<F, Fut, R>(&self, manager: &AccountManager<T>, query: &F) -> anyhow::Result<R>
+    where
+        F: Fn(Arc<Client<T>>) -> Fut,
+        Fut: Future<Output = anyhow::Result<R>>,
+    {
+        let client = self.client(manager, None).await?;
+
+        match query(client.clone()).await {
+            Err(e) if matches!(e.downcast_ref(), Some(WilmaError::SessionExpired)) => {
+                tracing::info!(account = %self.key, "logging in again");
+
+                let client = self.client(manager, Some(&client)).await?;
+                query(client).await
+            }
+            result => result,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::transport::{MemoryTransport, Method, Response};
+
+    fn server(transport: MemoryTransport, server: &str, session: &str) -> MemoryTransport {
+        transport
+            .with_response(
+                Method::Get,
+                format!("https://{}/index_json", server),
+                Response::new(
+                    200,
+                    r#"{ "LoginResult": "Failed", "SessionID": "abc", "ApiVersion": 13 }"#,
+                ),
+            )
+            .with_response(
+                Method::Post,
+                format!("https://{}/login", server),
+                Response::new(303, "").with_header("Set-Cookie", format!("Wilma2SID={}", session)),
+            )
+            .with_response(
+                Method::Get,
+                format!("https://{}", server),
+                Response::new(
+                    200,
+                    r#"<a class="text-style-link" href="/!01/">Anna</a>
+                       <a class="text-style-link" href="/!02/">Ben</a>"#,
+                ),
+            )
+    }
+
+    fn messages() -> Response {
+        Response::new(
+            200,
+            r#"{ "Messages": [
+                { "Id": 1, "Subject": "Read", "TimeStamp": "2022-03-14 12:30", "Status": 0 },
+                { "Id": 2, "Subject": "Unread", "TimeStamp": "2022-03-15 12:30", "Status": 1 }
+            ] }"#,
+        )
+    }
+
+    fn credentials<'a>(server: &'a str, username: &'a str) -> Credentials<'a> {
+        Credentials {
+            username,
+            password: "hunter2",
+            server,
+        }
+    }
+
+    #[tokio::test]
+    async fn isolates_failing_accounts() {
+        let transport = server(MemoryTransport::new(), "a.example.com", "a").with_response(
+            Method::Get,
+            "https://a.example.com/!02/messages/list",
+            messages(),
+        );
+        let transport = Arc::new(transport);
+
+        let shared = transport.clone();
+        let mut manager = AccountManager::with_transport(move || Ok(shared.clone()));
+        let ben = manager.add(credentials("a.example.com", "parent"), Some("ben"));
+        let other = manager.add(credentials("b.example.com", "parent"), None);
+
+        let results = manager.unread_messages().await;
+
+        assert_eq!(results[0].0, ben);
+        let unread = results[0].1.as_ref().unwrap();
+        assert_eq!(unread.len(), 1);
+        assert_eq!(unread[0].subject, "Unread");
+
+        assert_eq!(results[1].0, other);
+        assert!(results[1].1.is_err());
+    }
+
+    #[tokio::test]
+    async fn logs_in_again_when_the_session_expires() {
+        let transport = server(MemoryTransport::new(), "a.example.com", "a")
+            .with_response(
+                Method::Get,
+                "https://a.example.com/!01/messages/list",
+                Response::new(302, "").with_header("Location", "/?checkcookie"),
+            )
+            .with_response(
+                Method::Get,
+                "https://a.example.com/!01/messages/list",
+                messages(),
+            );
+        let transport = Arc::new(transport);
+
+        let shared = transport.clone();
+        let mut manager = AccountManager::with_transport(move || Ok(shared.clone()));
+        manager.add(credentials("a.example.com", "parent"), None);
+
+        let results = manager.unread_messages().await;
+        assert_eq!(results[0].1.as_ref().unwrap().len(), 1);
+
+        let logins = transport
+            .requests()
+            .iter()
+            .filter(|r| r.method == Method::Post)
+            .count();
+        assert_eq!(logins, 2);
+    }
+}
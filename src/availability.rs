@@ -0,0 +1,253 @@
+//! Free time in one or more schedules, for example when two siblings are both free, or a
+//! shared free period for a teachers' meeting.
+
+use crate::wilma::{
+    schedule::{Time, TimeSpan, Weekday},
+    Calendar, Schedule,
+};
+use chrono::{Datelike, Duration, NaiveDate};
+
+/// A free window on a specific date.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct FreeWindow {
+    pub date: NaiveDate,
+    pub span: TimeSpan,
+}
+
+/// Free time analysis over schedules. A time is free when none of the schedules has a
+/// reservation going on.
+#[derive(Debug, Clone)]
+pub struct FreeTime<'a> {
+    schedules: Vec<&'a Schedule>,
+    day: TimeSpan,
+    weekdays: Vec<Weekday>,
+    calendar: Option<&'a Calendar>,
+}
+
+impl<'a> FreeTime<'a> {
+    /// Analyses the given schedules between 8:00 and 16:00 on weekdays from Monday to Friday.
+    pub fn new<I: IntoIterator<Item = &'a Schedule>>(schedules: I) -> Self {
+        Self {
+            schedules: schedules.into_iter().collect(),
+            day: TimeSpan {
+                start: Time::new(8, 0).unwrap_or_default(),
+                end: Time::new(16, 0).unwrap_or_default(),
+            },
+            weekdays: Weekday::ALL[..5].to_vec(),
+            calendar: None,
+        }
+    }
+
+    /// Sets the part of the day free time is looked for in.
+    pub fn with_day(mut self, day: TimeSpan) -> Self {
+        self.day = day;
+        self
+    }
+
+    /// Sets the weekdays free time is looked for on.
+    pub fn with_weekdays(mut self, weekdays: &[Weekday]) -> Self {
+        self.weekdays = weekdays.to_vec();
+        self
+    }
+
+    /// Skips the holidays of the calendar in `FreeTime::windows`.
+    pub fn with_calendar(mut self, calendar: &'a Calendar) -> Self {
+        self.calendar = Some(calendar);
+        self
+    }
+
+    /// The times on a weekday when at least one of the schedules has a reservation, merged and
+    /// in order.
+    pub fn busy(&self, weekday: Weekday) -> Vec<TimeSpan> {
+        let mut spans: Vec<TimeSpan> = self
+            .schedules
+            .iter()
+            .flat_map(|s| s.reservations())
+            .filter(|r| r.weekday == weekday)
+            .map(|r| r.span())
+            .filter(|s| s.start < s.end)
+            .collect();
+        spans.sort();
+
+        let mut merged: Vec<TimeSpan> = Vec::new();
+
+        for span in spans {
+            match merged.last_mut() {
+                Some(last) if span.start <= last.end => last.end = last.end.max(span.end),
+                _ => merged.push(span),
+            }
+        }
+
+        merged
+    }
+
+    /// The free times between the first and the last reservation of a weekday.
+    pub fn gaps(&self, weekday: Weekday) -> Vec<TimeSpan> {
+        self.busy(weekday)
+            .windows(2)
+            .map(|pair| TimeSpan {
+                start: pair[0].end,
+                end: pair[1].start,
+            })
+            .collect()
+    }
+
+    /// The free times of a weekday within the part of the day set with `FreeTime::with_day`.
+    pub fn free_periods(&self, weekday: Weekday) -> Vec<TimeSpan> {
+        let mut free = Vec::new();
+        let mut start = self.day.start;
+
+        for busy in self.busy(weekday) {
+            if busy.start > start {
+                free.push(TimeSpan {
+                    start,
+                    end: busy.start.min(self.day.end),
+                });
+            }
+
+            start = start.max(busy.end);
+        }
+
+        free.push(TimeSpan {
+            start,
+            end: self.day.end,
+        });
+        free.retain(|s| s.start < s.end);
+
+        free
+    }
+
+    /// The free windows of at least `min` between `start` and `end` (inclusive), skipping the
+    /// weekdays that aren't looked at, the holidays of the calendar and the dates no term of the
+    /// schedules contains, like `Schedule::expand`.
+    pub fn windows(&self, start: NaiveDate, end: NaiveDate, min: Duration) -> Vec<FreeWindow> {
+        let mut windows = Vec::new();
+
+        for date in start.iter_days().take_while(|d| *d <= end) {
+            let weekday = Weekday::from(date.weekday());
+
+            if !self.weekdays.contains(&weekday)
+                || self.calendar.is_some_and(|c| c.is_holiday(date))
+                || !self.in_term(date)
+            {
+                continue;
+            }
+
+            windows.extend(
+                self.free_periods(weekday)
+                    .into_iter()
+                    .filter(|s| s.duration() >= min)
+                    .map(|span| FreeWindow { date, span }),
+            );
+        }
+
+        windows
+    }
+
+    /// Whether a term of any of the schedules contains the date.
+    fn in_term(&self, date: NaiveDate) -> bool {
+        self.schedules
+            .iter()
+            .flat_map(|s| s.terms())
+            .any(|t| t.contains(date))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// A schedule with the given reservations, in a term from Monday 2022-10-17 to Friday.
+    fn schedule(reservations: &[(u32, &str, &str)]) -> Schedule {
+        let reservations: Vec<String> = reservations
+            .iter()
+            .enumerate()
+            .map(|(id, (day, start, end))| {
+                format!(
+                    r#"{{ "Day": {}, "Class": "", "Color": null, "Start": "{}", "End": "{}",
+                        "Groups": [], "ScheduleID": 1, "ReservationID": {} }}"#,
+                    day, start, end, id
+                )
+            })
+            .collect();
+
+        serde_json::from_str(&format!(
+            r#"{{ "Terms": [{{ "Name": "Syksy", "StartDate": "2022-10-17", "EndDate": "2022-10-21" }}],
+                  "Schedule": [{}] }}"#,
+            reservations.join(",")
+        ))
+        .unwrap()
+    }
+
+    fn span(start: &str, end: &str) -> TimeSpan {
+        TimeSpan::new(start.parse().unwrap(), end.parse().unwrap()).unwrap()
+    }
+
+    #[test]
+    fn finds_gaps_and_free_periods() {
+        let schedule = schedule(&[
+            (1, "08:15", "09:30"),
+            (1, "09:30", "10:45"),
+            (1, "12:00", "13:15"),
+            (1, "12:30", "14:00"),
+        ]);
+        let free_time = FreeTime::new([&schedule]);
+
+        assert_eq!(
+            free_time.busy(Weekday::Monday),
+            [span("8:15", "10:45"), span("12:00", "14:00")]
+        );
+        assert_eq!(free_time.gaps(Weekday::Monday), [span("10:45", "12:00")]);
+        assert_eq!(
+            free_time.free_periods(Weekday::Monday),
+            [
+                span("8:00", "8:15"),
+                span("10:45", "12:00"),
+                span("14:00", "16:00")
+            ]
+        );
+        assert_eq!(
+            free_time.free_periods(Weekday::Tuesday),
+            [span("8:00", "16:00")]
+        );
+    }
+
+    #[test]
+    fn finds_common_windows() {
+        let first = schedule(&[(1, "08:00", "10:00"), (1, "13:00", "15:00")]);
+        let second = schedule(&[(1, "09:00", "12:00"), (2, "08:00", "16:00")]);
+
+        let calendar: Calendar = serde_json::from_str(
+            r#"{
+                "Events": [
+                    { "Id": 1, "Type": "holiday", "Caption": "Syysloma",
+                      "StartDate": "2022-10-19", "EndDate": "2022-10-19" }
+                ]
+            }"#,
+        )
+        .unwrap();
+
+        let free_time = FreeTime::new([&first, &second]).with_calendar(&calendar);
+
+        // Monday to Sunday, with a holiday on Wednesday.
+        let monday = NaiveDate::from_ymd_opt(2022, 10, 17).unwrap();
+        let windows = free_time.windows(monday, monday + Duration::days(6), Duration::hours(1));
+        let dates: Vec<u32> = windows.iter().map(|w| w.date.day()).collect();
+
+        assert_eq!(dates, [17, 17, 20, 21]);
+
+        // The next week is after the term.
+        let next = free_time.windows(
+            monday + Duration::days(7),
+            monday + Duration::days(11),
+            Duration::hours(1),
+        );
+        assert!(next.is_empty());
+        assert_eq!(windows[0].span, span("12:00", "13:00"));
+        assert_eq!(windows[1].span, span("15:00", "16:00"));
+        assert_eq!(windows[2].span, span("8:00", "16:00"));
+
+        let long = free_time.windows(monday, monday, Duration::minutes(61));
+        assert!(long.is_empty());
+    }
+}
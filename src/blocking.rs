@@ -0,0 +1,354 @@
+//! A blocking (synchronous) version of the client, for programs that don't use async.
+//!
+//! Every method blocks the current thread until the underlying async call finishes. The client
+//! owns a single-threaded runtime, so it must not be used from within an async runtime.
+
+use crate::{
+    cache::ResponseCache,
+    parser::{core::Form, SelectorOverrides, Selectors},
+    policy::{Counters, RequestPolicy},
+    rooms::FreeRooms,
+    transport::ReqwestTransport,
+    wilma::{
+        schedule::{reservation::Time, Room, Teacher},
+        Calendar, Grade, Message, News, Note, Overview, Schedule, User,
+    },
+    ApiVersion, Credentials, Language, LoginOptions, ServerInfo, Session, SsoStep,
+};
+use chrono::NaiveDate;
+use std::error::Error as StdError;
+use std::fmt;
+use std::future::Future;
+use tokio::runtime::{Builder, Runtime};
+
+/// The blocking counterpart of `crate::probe`.
+pub fn probe(server: &str) -> anyhow::Result<ServerInfo> {
+    let runtime = Builder::new_current_thread().enable_all().build()?;
+    runtime.block_on(crate::probe(server))
+}
+
+/// The blocking counterpart of `crate::probe::probe_with_overrides`.
+pub fn probe_with_overrides(
+    server: &str,
+    overrides: &SelectorOverrides,
+) -> anyhow::Result<ServerInfo> {
+    let runtime = Builder::new_current_thread().enable_all().build()?;
+    let transport = ReqwestTransport::new()?;
+    runtime.block_on(crate::probe::probe_with_overrides(
+        &transport, server, overrides,
+    ))
+}
+
+/// The blocking counterpart of `crate::Login`.
+pub enum Login {
+    Complete(Client),
+    MfaRequired(PendingLogin),
+}
+
+/// The blocking counterpart of `crate::PendingLogin`.
+pub struct PendingLogin {
+    inner: crate::PendingLogin,
+    runtime: Runtime,
+}
+
+impl PendingLogin {
+    pub fn form(&self) -> &Form {
+        self.inner.form()
+    }
+
+    pub fn complete(self, code: &str) -> Result<Client, MfaRejected> {
+        match self.runtime.block_on(self.inner.complete(code)) {
+            Ok(inner) => Ok(Client {
+                inner,
+                runtime: self.runtime,
+            }),
+            Err(rejected) => Err(MfaRejected {
+                error: rejected.error,
+                pending: rejected.pending.map(|inner| {
+                    Box::new(PendingLogin {
+                        inner: *inner,
+                        runtime: self.runtime,
+                    })
+                }),
+            }),
+        }
+    }
+
+    #[cfg(feature = "totp")]
+    pub fn complete_with_totp(self, totp: &crate::totp::Totp) -> Result<Client, MfaRejected> {
+        self.complete(&totp.code())
+    }
+}
+
+/// The blocking counterpart of `crate::login::MfaRejected`.
+pub struct MfaRejected {
+    pub error: anyhow::Error,
+    pub pending: Option<Box<PendingLogin>>,
+}
+
+impl fmt::Debug for MfaRejected {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_struct("MfaRejected")
+            .field("error", &self.error)
+            .field("pending", &self.pending.is_some())
+            .finish()
+    }
+}
+
+impl fmt::Display for MfaRejected {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}", self.error)
+    }
+}
+
+impl StdError for MfaRejected {
+    fn source(&self) -> Option<&(dyn StdError + 'static)> {
+        self.error.source()
+    }
+}
+
+/// The blocking counterpart of `crate::Sso`.
+pub enum Sso {
+    Complete(Client),
+    Authorize(SsoLogin),
+}
+
+/// The blocking counterpart of `crate::SsoLogin`.
+pub struct SsoLogin {
+    inner: crate::SsoLogin,
+    runtime: Runtime,
+}
+
+impl SsoLogin {
+    pub fn step(&self) -> &SsoStep {
+        self.inner.step()
+    }
+
+    pub fn submit(self, values: &[(&str, &str)]) -> anyhow::Result<Sso> {
+        let sso = self.runtime.block_on(self.inner.submit(values))?;
+        Ok(Sso::new(sso, self.runtime))
+    }
+
+    pub fn complete_with_callback(self, callback_url: &str) -> anyhow::Result<Sso> {
+        let sso = self
+            .runtime
+            .block_on(self.inner.complete_with_callback(callback_url))?;
+        Ok(Sso::new(sso, self.runtime))
+    }
+}
+
+impl Sso {
+    fn new(sso: crate::Sso, runtime: Runtime) -> Self {
+        match sso {
+            crate::Sso::Complete(inner) => Sso::Complete(Client { inner, runtime }),
+            crate::Sso::Authorize(inner) => Sso::Authorize(SsoLogin { inner, runtime }),
+        }
+    }
+}
+
+/// The blocking counterpart of `crate::Client`. See its documentation for details on each
+/// method.
+pub struct Client {
+    inner: crate::Client,
+    runtime: Runtime,
+}
+
+impl Client {
+    pub fn login(credentials: Credentials<'_>) -> anyhow::Result<Self> {
+        Self::login_as(credentials, None)
+    }
+
+    pub fn login_as(credentials: Credentials<'_>, role: Option<&str>) -> anyhow::Result<Self> {
+        let runtime = Builder::new_current_thread().enable_all().build()?;
+        let transport = ReqwestTransport::new()?;
+        let inner = runtime.block_on(crate::Client::login_as(transport, credentials, role))?;
+
+        Ok(Self { inner, runtime })
+    }
+
+    pub fn start_login(credentials: Credentials<'_>, role: Option<&str>) -> anyhow::Result<Login> {
+        let runtime = Builder::new_current_thread().enable_all().build()?;
+
+        match runtime.block_on(crate::Client::start_login(credentials, role))? {
+            crate::Login::Complete(inner) => Ok(Login::Complete(Self { inner, runtime })),
+            crate::Login::MfaRequired(inner) => {
+                Ok(Login::MfaRequired(PendingLogin { inner, runtime }))
+            }
+        }
+    }
+
+    pub fn start_login_with_options(
+        credentials: Credentials<'_>,
+        options: &LoginOptions,
+    ) -> anyhow::Result<Login> {
+        let runtime = Builder::new_current_thread().enable_all().build()?;
+        let transport = ReqwestTransport::new()?;
+        let login = crate::Client::start_login_with_options(transport, credentials, options);
+
+        match runtime.block_on(login)? {
+            crate::Login::Complete(inner) => Ok(Login::Complete(Self { inner, runtime })),
+            crate::Login::MfaRequired(inner) => {
+                Ok(Login::MfaRequired(PendingLogin { inner, runtime }))
+            }
+        }
+    }
+
+    pub fn start_sso_with_options(
+        server: &str,
+        start_url: &str,
+        options: &LoginOptions,
+    ) -> anyhow::Result<Sso> {
+        let runtime = Builder::new_current_thread().enable_all().build()?;
+        let transport = ReqwestTransport::new()?;
+        let sso = runtime.block_on(crate::Client::start_sso_with_options(
+            transport, server, start_url, options,
+        ))?;
+
+        Ok(Sso::new(sso, runtime))
+    }
+
+    pub fn start_sso(server: &str, start_url: &str, role: Option<&str>) -> anyhow::Result<Sso> {
+        let runtime = Builder::new_current_thread().enable_all().build()?;
+        let sso = runtime.block_on(crate::Client::start_sso(server, start_url, role))?;
+
+        Ok(Sso::new(sso, runtime))
+    }
+
+    pub fn resume(session: Session) -> anyhow::Result<Self> {
+        let runtime = Builder::new_current_thread().enable_all().build()?;
+        let inner = crate::Client::resume(session)?;
+
+        Ok(Self { inner, runtime })
+    }
+
+    pub fn session(&self) -> Session {
+        self.inner.session()
+    }
+
+    /// The async client this client wraps, for use with `Client::block_on`.
+    pub fn as_async(&self) -> &crate::Client {
+        &self.inner
+    }
+
+    /// Runs a future on the client's runtime. Useful for the async APIs built on top of the
+    /// client, for example `client.block_on(store.sync(client.as_async()))`.
+    pub fn block_on<F: Future>(&self, future: F) -> F::Output {
+        self.runtime.block_on(future)
+    }
+
+    pub fn with_policy(mut self, policy: RequestPolicy) -> Self {
+        self.inner = self.inner.with_policy(policy);
+        self
+    }
+
+    pub fn counters(&self) -> Counters {
+        self.inner.counters()
+    }
+
+    pub fn api_version(&self) -> Option<ApiVersion> {
+        self.inner.api_version()
+    }
+
+    pub fn with_api_version(mut self, version: u32) -> anyhow::Result<Self> {
+        self.inner = self.inner.with_api_version(version)?;
+        Ok(self)
+    }
+
+    pub fn with_selectors(mut self, selectors: Selectors) -> Self {
+        self.inner = self.inner.with_selectors(selectors);
+        self
+    }
+
+    pub fn with_selector_overrides(mut self, overrides: &SelectorOverrides) -> Self {
+        self.inner = self.inner.with_selector_overrides(overrides);
+        self
+    }
+
+    pub fn with_language(mut self, language: Language) -> Self {
+        self.inner = self.inner.with_language(language);
+        self
+    }
+
+    pub fn language(&self) -> Option<Language> {
+        self.inner.language()
+    }
+
+    pub fn with_cache(mut self, cache: ResponseCache) -> Self {
+        self.inner = self.inner.with_cache(cache);
+        self
+    }
+
+    pub fn invalidate(&self, path: &str) {
+        self.inner.invalidate(path)
+    }
+
+    pub fn clear_cache(&self) {
+        self.inner.clear_cache()
+    }
+
+    pub fn get_user_profile(&self) -> anyhow::Result<User> {
+        self.block_on(self.inner.get_user_profile())
+    }
+
+    pub fn get_user_schedule(&self) -> anyhow::Result<Schedule> {
+        self.block_on(self.inner.get_user_schedule())
+    }
+
+    #[cfg(feature = "unstable-week-schedule")]
+    pub fn get_user_week_schedule(&self, date: NaiveDate) -> anyhow::Result<Schedule> {
+        self.block_on(self.inner.get_user_week_schedule(date))
+    }
+
+    pub fn get_teacher_schedule(&self, id: u32) -> anyhow::Result<Schedule> {
+        self.block_on(self.inner.get_teacher_schedule(id))
+    }
+
+    pub fn get_room_schedule(&self, id: u32) -> anyhow::Result<Schedule> {
+        self.block_on(self.inner.get_room_schedule(id))
+    }
+
+    pub fn get_schedule_of_teacher(&self, teacher: &Teacher) -> anyhow::Result<Schedule> {
+        self.block_on(self.inner.get_schedule_of_teacher(teacher))
+    }
+
+    pub fn get_schedule_of_room(&self, room: &Room) -> anyhow::Result<Schedule> {
+        self.block_on(self.inner.get_schedule_of_room(room))
+    }
+
+    pub fn get_class_schedule(&self, id: u32) -> anyhow::Result<Schedule> {
+        self.block_on(self.inner.get_class_schedule(id))
+    }
+
+    pub fn find_free_rooms(
+        &self,
+        date: NaiveDate,
+        start: &Time,
+        end: &Time,
+    ) -> anyhow::Result<FreeRooms> {
+        self.block_on(self.inner.find_free_rooms(date, start, end))
+    }
+
+    pub fn get_calendar(&self, start: NaiveDate, end: NaiveDate) -> anyhow::Result<Calendar> {
+        self.block_on(self.inner.get_calendar(start, end))
+    }
+
+    pub fn get_overview(&self) -> anyhow::Result<Overview> {
+        self.block_on(self.inner.get_overview())
+    }
+
+    pub fn get_messages(&self) -> anyhow::Result<Vec<Message>> {
+        self.block_on(self.inner.get_messages())
+    }
+
+    pub fn get_news(&self) -> anyhow::Result<Vec<News>> {
+        self.block_on(self.inner.get_news())
+    }
+
+    pub fn get_notes(&self) -> anyhow::Result<Vec<Note>> {
+        self.block_on(self.inner.get_notes())
+    }
+
+    pub fn get_grades(&self) -> anyhow::Result<Vec<Grade>> {
+        self.block_on(self.inner.get_grades())
+    }
+}
@@ -0,0 +1,82 @@
+use super::{without_query, CacheBackend, CachedResponse};
+use sha2::{Digest, Sha256};
+use std::fs;
+use std::path::PathBuf;
+
+/// A cache backend that stores every response as a JSON file in a directory, so the cache
+/// survives restarts.
+///
+/// The files are named after a hash of the URL, in a subdirectory named after a hash of the URL
+/// without its query. Responses may contain personal data, so the directory should only be
+/// readable by the user running the program.
+pub struct DiskCache {
+    directory: PathBuf,
+}
+
+impl DiskCache {
+    /// Uses the given directory, creating it if it doesn't exist.
+    pub fn new<P: Into<PathBuf>>(directory: P) -> anyhow::Result<Self> {
+        let directory = directory.into();
+        fs::create_dir_all(&directory)?;
+
+        Ok(Self { directory })
+    }
+
+    fn path(&self, key: &str) -> PathBuf {
+        let name = hash(key);
+        self.url_directory(key).join(format!("{}.json", name))
+    }
+
+    /// The subdirectory of the responses of a URL with any query.
+    fn url_directory(&self, key: &str) -> PathBuf {
+        self.directory.join(hash(without_query(key)))
+    }
+}
+
+fn hash(key: &str) -> String {
+    hex::encode(Sha256::digest(key.as_bytes()))
+}
+
+/// Whether a file name is a hash made by `hash`, so `DiskCache::clear` leaves other files alone.
+fn is_hash(name: &str) -> bool {
+    name.len() == 64 && name.bytes().all(|b| b.is_ascii_hexdigit())
+}
+
+// A cache is only an optimization, so I/O errors are treated as cache misses.
+impl CacheBackend for DiskCache {
+    fn get(&self, key: &str) -> Option<CachedResponse> {
+        let data = fs::read_to_string(self.path(key)).ok()?;
+        serde_json::from_str(&data).ok()
+    }
+
+    fn put(&self, key: &str, response: CachedResponse) {
+        if let Ok(data) = serde_json::to_string(&response) {
+            let _ = fs::create_dir_all(self.url_directory(key));
+            let _ = fs::write(self.path(key), data);
+        }
+    }
+
+    fn remove(&self, key: &str) {
+        let _ = fs::remove_file(self.path(key));
+    }
+
+    fn remove_all(&self, url: &str) {
+        let _ = fs::remove_dir_all(self.url_directory(url));
+    }
+
+    fn clear(&self) {
+        if let Ok(entries) = fs::read_dir(&self.directory) {
+            for entry in entries.flatten() {
+                let path = entry.path();
+                let hashed = path
+                    .file_name()
+                    .and_then(|n| n.to_str())
+                    .is_some_and(is_hash);
+
+                if hashed && path.is_dir() {
+                    let _ = fs::remove_dir_all(path);
+                }
+            }
+        }
+    }
+}
@@ -0,0 +1,41 @@
+use super::{without_query, CacheBackend, CachedResponse};
+use std::collections::HashMap;
+use std::sync::Mutex;
+
+/// A cache backend that keeps responses in memory for the lifetime of the client.
+#[derive(Default)]
+pub struct MemoryCache {
+    entries: Mutex<HashMap<String, CachedResponse>>,
+}
+
+impl CacheBackend for MemoryCache {
+    fn get(&self, key: &str) -> Option<CachedResponse> {
+        self.entries.lock().ok()?.get(key).cloned()
+    }
+
+    fn put(&self, key: &str, response: CachedResponse) {
+        if let Ok(mut entries) = self.entries.lock() {
+            entries.insert(key.into(), response);
+        }
+    }
+
+    fn remove(&self, key: &str) {
+        if let Ok(mut entries) = self.entries.lock() {
+            entries.remove(key);
+        }
+    }
+
+    fn remove_all(&self, url: &str) {
+        let url = without_query(url);
+
+        if let Ok(mut entries) = self.entries.lock() {
+            entries.retain(|key, _| without_query(key) != url);
+        }
+    }
+
+    fn clear(&self) {
+        if let Ok(mut entries) = self.entries.lock() {
+            entries.clear();
+        }
+    }
+}
@@ -0,0 +1,213 @@
+use serde::{Deserialize, Serialize};
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+pub mod disk;
+pub use disk::DiskCache;
+
+pub mod memory;
+pub use memory::MemoryCache;
+
+/// A successful response stored in a cache.
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub struct CachedResponse {
+    pub body: String,
+
+    /// The `ETag` header of the response, used for revalidating it with `If-None-Match`.
+    pub etag: Option<String>,
+
+    /// The `Last-Modified` header of the response, used for revalidating it with
+    /// `If-Modified-Since`.
+    pub last_modified: Option<String>,
+
+    /// When the response was stored or last revalidated, in seconds since the Unix epoch.
+    pub stored_at: u64,
+}
+
+impl CachedResponse {
+    pub fn new(body: String, etag: Option<String>, last_modified: Option<String>) -> Self {
+        Self {
+            body,
+            etag,
+            last_modified,
+            stored_at: now(),
+        }
+    }
+
+    /// How long ago the response was stored or last revalidated.
+    pub fn age(&self) -> Duration {
+        Duration::from_secs(now().saturating_sub(self.stored_at))
+    }
+
+    /// Whether the response can be revalidated with a conditional request.
+    pub fn is_revalidatable(&self) -> bool {
+        self.etag.is_some() || self.last_modified.is_some()
+    }
+
+    /// Marks the response as fresh again after the server confirmed it hasn't changed.
+    pub fn touch(&mut self) {
+        self.stored_at = now();
+    }
+}
+
+fn now() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|d| d.as_secs())
+        .unwrap_or_default()
+}
+
+/// Storage for cached responses, keyed by URL.
+pub trait CacheBackend: Send + Sync {
+    fn get(&self, key: &str) -> Option<CachedResponse>;
+
+    fn put(&self, key: &str, response: CachedResponse);
+
+    fn remove(&self, key: &str);
+
+    /// Removes the responses of a URL with any query, like `schedule?date=2022-10-17` for
+    /// `schedule`. A query in the given URL is ignored.
+    fn remove_all(&self, url: &str);
+
+    fn clear(&self);
+}
+
+/// The URL of a key without its query.
+pub(crate) fn without_query(key: &str) -> &str {
+    key.split('?').next().unwrap_or(key)
+}
+
+/// How long responses are used without asking the server, per endpoint.
+///
+/// Endpoints are matched by their path relative to the client's base URL, for example
+/// `"schedule/export/"`, and the longest matching prefix wins. The index page (the user profile)
+/// has the empty path, and the empty prefix `""` only matches it.
+#[derive(Clone, Debug)]
+pub struct CachePolicy {
+    default_ttl: Duration,
+    ttls: Vec<(String, Duration)>,
+}
+
+impl CachePolicy {
+    pub fn new(default_ttl: Duration) -> Self {
+        Self {
+            default_ttl,
+            ttls: Vec::new(),
+        }
+    }
+
+    /// Sets the TTL for every path starting with `prefix`.
+    pub fn with_ttl<T: Into<String>>(mut self, prefix: T, ttl: Duration) -> Self {
+        self.ttls.push((prefix.into(), ttl));
+        self
+    }
+
+    /// The TTL of the given path.
+    pub fn ttl_for(&self, path: &str) -> Duration {
+        self.ttls
+            .iter()
+            .filter(|(prefix, _)| match prefix.is_empty() {
+                true => path.is_empty(),
+                false => path.starts_with(prefix.as_str()),
+            })
+            .max_by_key(|(prefix, _)| prefix.len())
+            .map(|(_, ttl)| *ttl)
+            .unwrap_or(self.default_ttl)
+    }
+}
+
+impl Default for CachePolicy {
+    /// Everything is revalidated on every request, except for the index page and schedules,
+    /// which change rarely.
+    fn default() -> Self {
+        Self::new(Duration::ZERO)
+            .with_ttl("", Duration::from_secs(5 * 60))
+            .with_ttl("schedule/export/", Duration::from_secs(60 * 60))
+    }
+}
+
+/// A response cache, consisting of a backend and a policy.
+pub struct ResponseCache {
+    backend: Box<dyn CacheBackend>,
+    policy: CachePolicy,
+}
+
+impl ResponseCache {
+    pub fn new<B: CacheBackend + 'static>(backend: B, policy: CachePolicy) -> Self {
+        Self {
+            backend: Box::new(backend),
+            policy,
+        }
+    }
+
+    /// An in-memory cache with the default policy.
+    pub fn in_memory() -> Self {
+        Self::new(MemoryCache::default(), CachePolicy::default())
+    }
+
+    pub fn backend(&self) -> &dyn CacheBackend {
+        self.backend.as_ref()
+    }
+
+    pub fn policy(&self) -> &CachePolicy {
+        &self.policy
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn longest_prefix_wins() {
+        let policy = CachePolicy::new(Duration::from_secs(1))
+            .with_ttl("schedule/", Duration::from_secs(2))
+            .with_ttl("schedule/export/", Duration::from_secs(3));
+
+        assert_eq!(policy.ttl_for("overview"), Duration::from_secs(1));
+        assert_eq!(CachePolicy::default().ttl_for("overview"), Duration::ZERO);
+        assert_eq!(
+            CachePolicy::default().ttl_for(""),
+            Duration::from_secs(5 * 60)
+        );
+        assert_eq!(policy.ttl_for("schedule/events"), Duration::from_secs(2));
+        assert_eq!(
+            policy.ttl_for("schedule/export/students/1"),
+            Duration::from_secs(3)
+        );
+    }
+
+    #[test]
+    fn removes_urls_with_any_query() {
+        let directory = std::env::temp_dir().join(format!("wilma-cache-{}", std::process::id()));
+        let backends: [Box<dyn CacheBackend>; 2] = [
+            Box::new(MemoryCache::default()),
+            Box::new(DiskCache::new(&directory).unwrap()),
+        ];
+
+        for backend in backends {
+            let response = || CachedResponse::new("{}".into(), None, None);
+            backend.put("https://wilma.example.com/schedule", response());
+            backend.put(
+                "https://wilma.example.com/schedule?date=2022-10-17",
+                response(),
+            );
+            backend.put("https://wilma.example.com/schedule/events", response());
+
+            backend.remove_all("https://wilma.example.com/schedule?langid=2");
+            assert!(backend.get("https://wilma.example.com/schedule").is_none());
+            assert!(backend
+                .get("https://wilma.example.com/schedule?date=2022-10-17")
+                .is_none());
+            assert!(backend
+                .get("https://wilma.example.com/schedule/events")
+                .is_some());
+
+            backend.clear();
+            assert!(backend
+                .get("https://wilma.example.com/schedule/events")
+                .is_none());
+        }
+
+        let _ = std::fs::remove_dir_all(directory);
+    }
+}
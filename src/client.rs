@@ -1,147 +1,1048 @@
 // soon
 use crate::{
-    parser, utils,
-    wilma::{IndexResponse, Overview, Schedule, User},
-    Error as WilmaError,
+    cache::{CachedResponse, ResponseCache},
+    compat::{ApiVersion, Endpoint},
+    locale::with_langid,
+    login::{Login, LoginFlow, LoginOptions},
+    parser::{self, Page, SelectorOverrides, Selectors},
+    policy::{Counters, PolicyLayer, RequestPolicy},
+    redact::REDACTED,
+    rooms::{self, FreeRooms},
+    runtime::{self, Timer},
+    secret::Secret,
+    transport::{CookieJar, DefaultTransport, Request, Response, Transport},
+    utils,
+    wilma::{
+        schedule::{reservation::Time, Room, Teacher},
+        Calendar, Grade, IndexResponse, Message, News, Note, Overview, Schedule, User,
+    },
+    Error as WilmaError, Language,
 };
-use serde::{Deserialize, Serialize};
-use serde_json::{from_str as string_to_json, Value};
-use std::collections::HashMap;
+use chrono::NaiveDate;
+use serde::de::DeserializeOwned;
+use serde_json::Value;
+use std::fmt;
 use std::sync::Arc;
-
-use reqwest::{
-    cookie::{Cookie, Jar},
-    redirect::Policy,
-    Url,
-};
+use std::time::Instant;
+use tracing::Instrument;
 
 /// A struct for grouping all of the credentials together.
 /// Username: Used for representing the username on the Wilma server.
 /// Password: Password used for the Wilma account.
 /// Server: The Wilma server, turku.inschool.fi, for example.
-#[derive(Serialize, Deserialize, Clone, Debug)]
+///
+/// The password is redacted from the `Debug` output. See `OwnedCredentials` for keeping
+/// credentials around.
+#[derive(Clone, Copy)]
 pub struct Credentials<'a> {
     pub username: &'a str,
     pub password: &'a str,
     pub server: &'a str,
 }
 
+impl fmt::Debug for Credentials<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_struct("Credentials")
+            .field("username", &self.username)
+            .field("password", &REDACTED)
+            .field("server", &self.server)
+            .finish()
+    }
+}
+
+/// A logged in session, for resuming it later without logging in again. See `Client::session`.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Session {
+    /// The base URL of the chosen role, for example `https://turku.inschool.fi/!0123456/`.
+    pub base_url: String,
+
+    /// The `Wilma2SID` cookie.
+    pub id: Secret,
+}
+
 /// The main layer for interacting with Wilma.
 /// Documentation is a work-in-progress.
-pub struct Client {
-    http: reqwest::Client,
+///
+/// The client is generic over the `Transport` used for sending HTTP requests, which is `reqwest`
+/// by default (with the `reqwest` feature). See `Client::login_with` for using another one.
+pub struct Client<T: Transport = DefaultTransport> {
+    transport: T,
     base_url: String,
+    session: Secret,
+    cache: Option<ResponseCache>,
+    policy: PolicyLayer,
+    api_version: Option<ApiVersion>,
+    selectors: Arc<Selectors>,
+    language: Option<Language>,
 }
 
+#[cfg(feature = "reqwest")]
 impl Client {
     pub async fn login(credentials: Credentials<'_>) -> anyhow::Result<Self> {
-        let http_builder = reqwest::Client::builder().redirect(Policy::none());
-        let http = http_builder.build()?;
+        Self::login_with(DefaultTransport::new()?, credentials).await
+    }
+
+    /// Starts logging in, for accounts that may use two-step verification. See
+    /// `Client::start_login_with`.
+    pub async fn start_login(
+        credentials: Credentials<'_>,
+        role: Option<&str>,
+    ) -> anyhow::Result<Login> {
+        Self::start_login_with(DefaultTransport::new()?, credentials, role).await
+    }
+
+    /// Resumes a session without logging in. Requests fail with `Error::SessionExpired` if the
+    /// session is no longer valid.
+    pub fn resume(session: Session) -> anyhow::Result<Self> {
+        Ok(Self::resume_with(DefaultTransport::new()?, session))
+    }
+}
+
+impl<T: Transport> Client<T> {
+    /// Logs in using the given transport.
+    pub async fn login_with(transport: T, credentials: Credentials<'_>) -> anyhow::Result<Self> {
+        Self::login_as(transport, credentials, None).await
+    }
+
+    /// Logs in using the given transport, choosing a role for accounts that have several, for
+    /// example a guardian with multiple children. The role is matched against the identity
+    /// (`!0123456`) or the name of the role. Without a role, the first one is chosen.
+    ///
+    /// Fails with `Error::MfaRequired` if the account uses two-step verification; see
+    /// `Client::start_login_with` for those.
+    pub async fn login_as(
+        transport: T,
+        credentials: Credentials<'_>,
+        role: Option<&str>,
+    ) -> anyhow::Result<Self> {
+        match Self::start_login_with(transport, credentials, role).await? {
+            Login::Complete(client) => Ok(client),
+            Login::MfaRequired(_) => Err(WilmaError::MfaRequired.into()),
+        }
+    }
+
+    /// Starts logging in using the given transport. If the account uses two-step verification,
+    /// the returned `PendingLogin` is completed with a verification code. See `Client::login_as`
+    /// for roles.
+    pub async fn start_login_with(
+        transport: T,
+        credentials: Credentials<'_>,
+        role: Option<&str>,
+    ) -> anyhow::Result<Login<T>> {
+        let options = LoginOptions {
+            role: role.map(String::from),
+            ..Default::default()
+        };
+
+        Self::start_login_with_options(transport, credentials, &options).await
+    }
+
+    /// Starts logging in like `Client::start_login_with`, with a language and selector
+    /// overrides. See `LoginOptions`.
+    #[tracing::instrument(
+        name = "login",
+        skip(transport, credentials, options),
+        fields(server = credentials.server, username = credentials.username)
+    )]
+    pub async fn start_login_with_options(
+        transport: T,
+        credentials: Credentials<'_>,
+        options: &LoginOptions,
+    ) -> anyhow::Result<Login<T>> {
+        let policy = PolicyLayer::new(RequestPolicy::default(), runtime::default_timer());
 
         let url = utils::verify_url(&credentials.server);
 
         let index_path = format!("{}/index_json", url);
 
         // Retrieving a session ID from https://WILMA_SERVER/index_json.
-        let index_response: IndexResponse = string_to_json(&utils::get(&index_path).await?)?;
+        let index_response = send(&transport, &policy, Request::get(index_path)).await?;
+        let index_response: IndexResponse = parse_json("index_json", &index_response.text())?;
+
+        // Don't send the password to a server the client can't talk to.
+        let api_version = ApiVersion::new(index_response.api_version())?;
 
         let session_id = index_response.session_id();
 
-        let mut login_info: HashMap<&str, &str> = HashMap::new();
-        login_info.insert("Login", &credentials.username);
-        login_info.insert("Password", &credentials.password);
-        login_info.insert("SESSIONID", session_id);
-        login_info.insert("CompleteJson", "");
+        let login_info = [
+            ("Login", credentials.username),
+            ("Password", credentials.password),
+            ("SESSIONID", session_id.as_str()),
+            ("CompleteJson", ""),
+        ];
 
-        let login_path = format!("{}/login", url);
+        let login_path = with_langid(&format!("{}/login", url), options.language);
+        let login_request = Request::post_form(&login_path, &login_info);
+        let login_response = send(&transport, &policy, login_request).await?;
 
-        let login_response = http
-            .post(login_path)
-            .form(&login_info)
-            .header("Content-Type", "application/x-www-form-urlencoded")
-            .send()
-            .await?;
+        let flow = LoginFlow {
+            transport,
+            policy,
+            server_url: url,
+            api_version: Some(api_version),
+            role: options.role.clone(),
+            cookies: CookieJar::new(),
+            overrides: options.overrides.clone(),
+            language: options.language,
+        };
 
-        let cookies: Vec<Cookie> = login_response.cookies().collect();
+        flow.finish(&login_path, login_response).await
+    }
 
-        // OK for now.
-        let cookie = cookies
-            .iter()
-            .find(|c| c.name() == "Wilma2SID")
-            .ok_or(WilmaError::InvalidCredentials)?;
+    /// Creates a client for a session that was just logged in, selecting the role.
+    pub(crate) async fn from_login(flow: LoginFlow<T>, session: String) -> anyhow::Result<Self> {
+        let selectors = flow.selectors();
+        let LoginFlow {
+            transport,
+            policy,
+            server_url,
+            api_version,
+            role,
+            language,
+            ..
+        } = flow;
+        let role = role.as_deref();
 
-        let cookie_jar = Arc::new(Jar::default());
-        let cookie_url = url.clone().parse::<Url>()?;
+        // Get an "identity" string which is appended to the base URL.
+        let identity = async {
+            let request = Request::get(with_langid(&server_url, language))
+                .header("Cookie", session_cookie(&session));
+            let response = send(&transport, &policy, request).await?;
 
-        let cookie_string = format!("Wilma2SID={}", cookie.value());
+            let page = Page::with_selectors(&response.text(), selectors.clone());
 
-        cookie_jar.add_cookie_str(&cookie_string, &cookie_url);
+            let role = match role {
+                Some(role) => role,
+                None => return parser::core::parse_identity(&page),
+            };
 
-        // Re-initiate the client with the Wilma2SID cookie.
-        let builder = reqwest::Client::builder().redirect(Policy::none());
-        let client = builder.cookie_provider(cookie_jar).build()?;
+            parser::core::parse_roles(&page)
+                .into_iter()
+                .find(|(identity, name)| identity == role || name.eq_ignore_ascii_case(role))
+                .map(|(identity, _)| format!("{}/", identity))
+                .ok_or_else(|| anyhow::Error::from(WilmaError::RoleNotFound))
+        }
+        .instrument(tracing::debug_span!("select_role"))
+        .await?;
 
-        // Get an "identity" string which is appended to the base URL.
-        let res = client.get(url.clone()).send().await?.text().await?;
-        let identity = parser::core::parse_identity(&res);
+        tracing::info!("logged in");
 
-        let appended_url = format!("{}/{}", url, identity);
+        let appended_url = format!("{}/{}", server_url, identity);
 
         Ok(Self {
+            transport,
             base_url: appended_url,
-            http: client,
+            session: Secret::new(session),
+            cache: None,
+            policy,
+            api_version,
+            selectors,
+            language,
         })
     }
 
+    /// Resumes a session using the given transport. See `Client::resume`.
+    pub fn resume_with(transport: T, session: Session) -> Self {
+        Self {
+            transport,
+            base_url: session.base_url.clone(),
+            session: session.id.clone(),
+            cache: None,
+            policy: PolicyLayer::new(RequestPolicy::default(), runtime::default_timer()),
+            api_version: None,
+            selectors: Selectors::builtin(),
+            language: None,
+        }
+    }
+
+    /// The current session, for storing it and resuming it later with `Client::resume`.
+    pub fn session(&self) -> Session {
+        Session {
+            base_url: self.base_url.clone(),
+            id: self.session.clone(),
+        }
+    }
+
+    /// The transport the client sends its requests with.
+    pub fn transport(&self) -> &T {
+        &self.transport
+    }
+
+    /// Sets the rate limit, concurrency limit and retry settings of the client.
+    pub fn with_policy(mut self, policy: RequestPolicy) -> Self {
+        self.policy = PolicyLayer::new(policy, self.policy.timer());
+        self
+    }
+
+    /// Sets the timer used for rate limiting and retries. The default timer works with every
+    /// executor; see `runtime` for others.
+    pub fn with_timer(mut self, timer: Arc<dyn Timer>) -> Self {
+        self.policy = PolicyLayer::new(self.policy.policy().clone(), timer);
+        self
+    }
+
+    /// Counters of the requests sent by the client, including retries.
+    pub fn counters(&self) -> Counters {
+        self.policy.counters()
+    }
+
+    /// The API version the server reported when logging in. Resumed sessions don't know it, and
+    /// the latest version is assumed unless it's set with `Client::with_api_version`.
+    pub fn api_version(&self) -> Option<ApiVersion> {
+        self.api_version
+    }
+
+    /// Sets the API version of the server, for example for a resumed session. Resets the
+    /// selectors to the built-in ones of the version. Fails with `Error::UnsupportedApiVersion`
+    /// if the client can't talk to the version.
+    pub fn with_api_version(mut self, version: u32) -> anyhow::Result<Self> {
+        let version = ApiVersion::new(version)?;
+        self.api_version = Some(version);
+        self.selectors = Arc::new(version.selectors());
+
+        Ok(self)
+    }
+
+    /// Sets the selectors the HTML pages are parsed with.
+    pub fn with_selectors(mut self, selectors: Selectors) -> Self {
+        self.selectors = Arc::new(selectors);
+        self
+    }
+
+    /// Applies selector overrides for the API version of the server. See `SelectorOverrides`.
+    pub fn with_selector_overrides(self, overrides: &SelectorOverrides) -> Self {
+        let selectors = overrides.resolve(self.api_version.map(|v| v.number()));
+        self.with_selectors(selectors)
+    }
+
+    /// Asks Wilma to send its pages in a language with the `langid` parameter, so captions like
+    /// `Room::long_caption` arrive in it. Without a language, Wilma uses the default language of
+    /// the server. Responses are cached separately for every language.
+    pub fn with_language(mut self, language: Language) -> Self {
+        self.language = Some(language);
+        self
+    }
+
+    pub fn language(&self) -> Option<Language> {
+        self.language
+    }
+
+    /// Caches GET responses in the given cache. Without a cache, every call hits the server.
+    pub fn with_cache(mut self, cache: ResponseCache) -> Self {
+        self.cache = Some(cache);
+        self
+    }
+
+    /// Removes the cached responses of a path relative to the base URL, in every language and
+    /// with any query, for example `"schedule/export/students/123456"` also removes the week
+    /// schedules. The client itself doesn't change anything in Wilma, so call this after
+    /// something was changed through Wilma's web pages or another client, for example
+    /// `"messages/list"` after a message was read or sent.
+    pub fn invalidate(&self, path: &str) {
+        if let Some(cache) = &self.cache {
+            cache
+                .backend()
+                .remove_all(&format!("{}{}", self.base_url, path));
+        }
+    }
+
+    /// Removes all cached responses.
+    pub fn clear_cache(&self) {
+        if let Some(cache) = &self.cache {
+            cache.backend().clear();
+        }
+    }
+
     pub async fn get_user_profile(&self) -> anyhow::Result<User> {
         // Sending a GET request to the index gives you a page with your profile information.
-        let response = self
-            .http
-            .get(self.base_url.clone())
-            .send()
-            .await?
-            .text()
-            .await?;
+        let (status, response) = self.fetch(&self.base_url).await?;
+        check_status(status, &self.base_url)?;
 
         use parser::user as parser;
 
-        let name = parser::parse_name(&response);
-        let school = parser::parse_school(&response);
-        let formkey = parser::parse_formkey(&response);
+        let page = Page::with_selectors(&response, self.selectors.clone());
+        let name = parser::parse_name(&page)?;
+        let school = parser::parse_school(&page)?;
+        let formkey = parser::parse_formkey(&page)?;
 
         Ok(User::new(name, school, formkey))
     }
 
-    pub async fn get_user_schedule(&self) -> anyhow::Result<()> {
+    pub async fn get_user_schedule(&self) -> anyhow::Result<Schedule> {
+        self.fetch_user_schedule(None).await
+    }
+
+    /// Returns the user's schedule for the week `date` is in, with cancellations, substitute
+    /// teachers and room changes. See `Schedule::changes_from`.
+    #[cfg(feature = "unstable-week-schedule")]
+    pub async fn get_user_week_schedule(&self, date: NaiveDate) -> anyhow::Result<Schedule> {
+        self.fetch_user_schedule(Some(date)).await
+    }
+
+    /// Returns the user's regular schedule, or the schedule of the week `date` is in.
+    async fn fetch_user_schedule(&self, date: Option<NaiveDate>) -> anyhow::Result<Schedule> {
         let profile = self.get_user_profile().await?;
 
-        let url = &format!(
+        let mut url = format!(
             "{}schedule/export/students/{}",
-            self.base_url.clone(),
+            self.base_url,
             profile.user_id()
         );
 
-        let response = self.http.get(url).send().await?.text().await?;
+        if let Some(date) = date {
+            url.push_str(&format!("?date={}", date.format("%Y-%m-%d")));
+        }
+
+        let (status, response) = self.fetch(&url).await?;
+        check_status(status, &url)?;
 
-        let debug_json: Value = serde_json::from_str(&response).unwrap();
+        parse_json("schedule", &response)
+    }
 
-        println!("{}", serde_json::to_string_pretty(&debug_json).unwrap());
+    /// Returns the schedule of the teacher with the given ID. See `Schedule::teachers` for
+    /// teachers whose schedules are visible.
+    pub async fn get_teacher_schedule(&self, id: u32) -> anyhow::Result<Schedule> {
+        self.get_schedule("teachers", id).await
+    }
 
-        let schedule: Schedule = serde_json::from_str(&response).unwrap();
+    /// Returns the schedule of the room with the given ID. See `Schedule::rooms` for rooms whose
+    /// schedules are visible.
+    pub async fn get_room_schedule(&self, id: u32) -> anyhow::Result<Schedule> {
+        self.get_schedule("rooms", id).await
+    }
 
-        println!("{:#?}", schedule);
+    /// Returns the schedule of a teacher, failing with `Error::ScheduleNotVisible` without a
+    /// request if the schedule isn't visible to the user.
+    pub async fn get_schedule_of_teacher(&self, teacher: &Teacher) -> anyhow::Result<Schedule> {
+        if !teacher.schedule_visible {
+            return Err(WilmaError::ScheduleNotVisible.into());
+        }
 
-        Ok(())
+        self.get_teacher_schedule(teacher.id).await
+    }
+
+    /// Returns the schedule of a room, failing with `Error::ScheduleNotVisible` without a request
+    /// if the schedule isn't visible to the user.
+    pub async fn get_schedule_of_room(&self, room: &Room) -> anyhow::Result<Schedule> {
+        if !room.schedule_visible() {
+            return Err(WilmaError::ScheduleNotVisible.into());
+        }
+
+        self.get_room_schedule(room.id()).await
+    }
+
+    /// Returns the schedule of the class with the given ID.
+    pub async fn get_class_schedule(&self, id: u32) -> anyhow::Result<Schedule> {
+        self.get_schedule("classes", id).await
+    }
+
+    /// Finds the rooms that are free on `date` between `start` and `end`. See
+    /// `rooms::find_free_rooms`.
+    pub async fn find_free_rooms(
+        &self,
+        date: NaiveDate,
+        start: &Time,
+        end: &Time,
+    ) -> anyhow::Result<FreeRooms> {
+        rooms::find_free_rooms(self, date, start, end, rooms::DEFAULT_CONCURRENCY).await
+    }
+
+    async fn get_schedule(&self, owner: &str, id: u32) -> anyhow::Result<Schedule> {
+        let url = &format!("{}schedule/export/{}/{}", self.base_url.clone(), owner, id);
+
+        let (status, response) = self.fetch(url).await?;
+
+        // Wilma refuses to export schedules that aren't visible to the user.
+        if status == 403 {
+            return Err(WilmaError::ScheduleNotVisible.into());
+        }
+
+        check_status(status, url)?;
+        parse_json("schedule", &response)
+    }
+
+    /// Returns the school calendar (events, holidays, exam weeks and period boundaries) between
+    /// `start` and `end`.
+    pub async fn get_calendar(&self, start: NaiveDate, end: NaiveDate) -> anyhow::Result<Calendar> {
+        let url = &format!(
+            "{}schedule/events?start={}&end={}",
+            self.base_url.clone(),
+            start.format("%Y-%m-%d"),
+            end.format("%Y-%m-%d")
+        );
+
+        let (status, response) = self.fetch(url).await?;
+        check_status(status, url)?;
+
+        parse_json("calendar", &response)
     }
 
     pub async fn get_overview(&self) -> anyhow::Result<Overview> {
-        let url = &format!("{}overview", self.base_url.clone());
-        let response = self.http.get(url).send().await?.text().await?;
+        let json = self.get_endpoint(Endpoint::Overview).await?;
+
+        let _span = tracing::trace_span!("parse", what = "overview").entered();
+        Ok(serde_json::from_value(json)
+            .inspect_err(|e| tracing::warn!(error = %e, "failed to parse"))?)
+    }
+
+    /// Returns the messages in the user's inbox.
+    pub async fn get_messages(&self) -> anyhow::Result<Vec<Message>> {
+        self.get_list(Endpoint::Messages).await
+    }
+
+    /// Returns the news articles visible to the user.
+    pub async fn get_news(&self) -> anyhow::Result<Vec<News>> {
+        self.get_list(Endpoint::News).await
+    }
+
+    /// Returns the notes (attendance markings) given to the user.
+    pub async fn get_notes(&self) -> anyhow::Result<Vec<Note>> {
+        self.get_list(Endpoint::Notes).await
+    }
+
+    /// Returns the user's course grades.
+    pub async fn get_grades(&self) -> anyhow::Result<Vec<Grade>> {
+        self.get_list(Endpoint::Grades).await
+    }
+
+    /// Wilma wraps lists in an object, for example `{ "Messages": [...] }`. A missing key
+    /// fails with `Error::UnexpectedPage`, so it isn't mistaken for an empty list.
+    async fn get_list<D: DeserializeOwned>(&self, endpoint: Endpoint) -> anyhow::Result<Vec<D>> {
+        let key = endpoint.list_key().unwrap_or_default();
+        let mut json = self.get_endpoint(endpoint).await?;
+
+        let _span = tracing::trace_span!("parse", what = key).entered();
+
+        match json.get_mut(key) {
+            Some(list) => Ok(serde_json::from_value(list.take())
+                .inspect_err(|e| tracing::warn!(error = %e, "failed to parse"))?),
+            None => Err(parser::missing(&format!("{} list", key))),
+        }
+    }
+
+    /// Returns the JSON of an endpoint from its path in the server's API version, with the
+    /// fields renamed to their names in the latest version.
+    pub(crate) async fn get_endpoint(&self, endpoint: Endpoint) -> anyhow::Result<Value> {
+        let version = self.api_version.unwrap_or_default();
+        let mut json = self.get_json(version.path(endpoint)).await?;
+        version.normalize(endpoint, &mut json);
+
+        Ok(json)
+    }
+
+    /// Returns the raw JSON of an endpoint relative to the base URL, as sent by Wilma.
+    pub(crate) async fn get_json(&self, path: &str) -> anyhow::Result<Value> {
+        let url = &format!("{}{}", self.base_url.clone(), path);
+        let (status, response) = self.fetch(url).await?;
+        check_status(status, url)?;
+
+        parse_json(path, &response)
+    }
+
+    /// Sends a GET request, going through the response cache if one is set. Fresh cached
+    /// responses are returned without a request, and stale ones are revalidated with
+    /// `If-None-Match`/`If-Modified-Since` when the server sent an `ETag`/`Last-Modified` header.
+    async fn fetch(&self, url: &str) -> anyhow::Result<(u16, String)> {
+        let path = url.strip_prefix(&self.base_url).unwrap_or(url);
+
+        // The language is part of the URL, so responses are cached per language.
+        let url = &with_langid(url, self.language);
+        let mut request = Request::get(url).header("Cookie", session_cookie(self.session.expose()));
+
+        let cache = match &self.cache {
+            Some(cache) => cache,
+            None => {
+                let response = check_session(send(&self.transport, &self.policy, request).await?)?;
+                return Ok((response.status, response.text()));
+            }
+        };
+
+        let cached = cache.backend().get(url);
+
+        if let Some(cached) = &cached {
+            if cached.age() < cache.policy().ttl_for(path) {
+                utils::record_request("GET", url, 200, Instant::now(), &cached.body, true);
+                return Ok((200, cached.body.clone()));
+            }
+
+            if let Some(etag) = &cached.etag {
+                request = request.header("If-None-Match", etag);
+            }
+
+            if let Some(last_modified) = &cached.last_modified {
+                request = request.header("If-Modified-Since", last_modified);
+            }
+        }
+
+        let response = check_session(send(&self.transport, &self.policy, request).await?)?;
+
+        if let (304, Some(mut cached)) = (response.status, cached) {
+            cached.touch();
+            let body = cached.body.clone();
+            cache.backend().put(url, cached);
+
+            return Ok((200, body));
+        }
+
+        let body = response.text();
+
+        if response.is_success() {
+            let etag = response.header("ETag").map(String::from);
+            let last_modified = response.header("Last-Modified").map(String::from);
+
+            let cached = CachedResponse::new(body.clone(), etag, last_modified);
+            cache.backend().put(url, cached);
+        }
+
+        Ok((response.status, body))
+    }
+}
+
+/// Fails with the status and the URL if Wilma didn't respond with success.
+fn check_status(status: u16, url: &str) -> anyhow::Result<()> {
+    if !(200..300).contains(&status) {
+        anyhow::bail!("Wilma responded with status {} to {}.", status, url);
+    }
+
+    Ok(())
+}
+
+fn session_cookie(session: &str) -> String {
+    format!("Wilma2SID={}", session)
+}
+
+/// Wilma redirects to the login page, or responds with 401, when the session has expired.
+fn check_session(response: Response) -> anyhow::Result<Response> {
+    if response.status == 401 || (300..400).contains(&response.status) && response.status != 304 {
+        tracing::warn!(status = response.status, "session expired");
+        return Err(WilmaError::SessionExpired.into());
+    }
+
+    Ok(response)
+}
+
+/// Sends a request through the policy layer, recording it as a tracing event.
+pub(crate) async fn send<T: Transport>(
+    transport: &T,
+    policy: &PolicyLayer,
+    request: Request,
+) -> anyhow::Result<Response> {
+    let started = Instant::now();
+    let (method, url) = (request.method, request.url.clone());
+
+    let response = policy.execute(transport, request).await?;
+
+    let body = String::from_utf8_lossy(&response.body);
+    utils::record_request(
+        method.as_str(),
+        &url,
+        response.status,
+        started,
+        &body,
+        false,
+    );
+
+    Ok(response)
+}
+
+/// Parses a JSON response inside a `parse` span, recording parse errors.
+pub(crate) fn parse_json<D: DeserializeOwned>(what: &str, body: &str) -> anyhow::Result<D> {
+    let _span = tracing::trace_span!("parse", what).entered();
+
+    Ok(
+        serde_json::from_str(body)
+            .inspect_err(|e| tracing::warn!(error = %e, "failed to parse"))?,
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::cache::{CachePolicy, MemoryCache};
+    use crate::transport::{MemoryTransport, Method};
+    use std::time::Duration;
+
+    const BASE_URL: &str = "https://wilma.example.com/!0123456/";
+
+    fn client(transport: MemoryTransport, ttl: Duration) -> Client<MemoryTransport> {
+        Client {
+            transport,
+            base_url: BASE_URL.into(),
+            session: Secret::new("session"),
+            cache: None,
+            policy: PolicyLayer::new(RequestPolicy::default(), runtime::default_timer()),
+            api_version: None,
+            selectors: Selectors::builtin(),
+            language: None,
+        }
+        .with_cache(ResponseCache::new(
+            MemoryCache::default(),
+            CachePolicy::new(ttl),
+        ))
+    }
+
+    fn header<'a>(request: &'a Request, name: &str) -> Option<&'a str> {
+        request
+            .headers
+            .iter()
+            .find(|(n, _)| n == name)
+            .map(|(_, v)| v.as_str())
+    }
+
+    #[tokio::test]
+    async fn logs_in() {
+        let transport = MemoryTransport::new()
+            .with_response(
+                Method::Get,
+                "https://wilma.example.com/index_json",
+                Response::new(
+                    200,
+                    r#"{ "LoginResult": "Failed", "SessionID": "abc", "ApiVersion": 13 }"#,
+                ),
+            )
+            .with_response(
+                Method::Post,
+                "https://wilma.example.com/login",
+                Response::new(303, "").with_header("Set-Cookie", "Wilma2SID=sid; path=/"),
+            )
+            .with_response(
+                Method::Get,
+                "https://wilma.example.com",
+                Response::new(
+                    200,
+                    r#"<a class="text-style-link" href="/!0123456/">Oppilas</a>"#,
+                ),
+            );
+
+        let credentials = Credentials {
+            username: "user",
+            password: "hunter2",
+            server: "wilma.example.com",
+        };
+
+        let client = Client::login_with(transport, credentials).await.unwrap();
+        assert_eq!(client.base_url, "https://wilma.example.com/!0123456/");
+
+        let requests = client.transport().requests();
+        let login = String::from_utf8(requests[1].body.clone()).unwrap();
+        assert!(login.contains("Password=hunter2"));
+        assert!(login.contains("SESSIONID=abc"));
+        assert_eq!(header(&requests[2], "Cookie"), Some("Wilma2SID=sid"));
+    }
+
+    #[tokio::test]
+    async fn selects_roles_with_selector_overrides() {
+        let transport = MemoryTransport::new()
+            .with_response(
+                Method::Get,
+                "https://wilma.example.com/index_json",
+                Response::new(
+                    200,
+                    r#"{ "LoginResult": "Failed", "SessionID": "abc", "ApiVersion": 13 }"#,
+                ),
+            )
+            .with_response(
+                Method::Post,
+                "https://wilma.example.com/login",
+                Response::new(303, "").with_header("Set-Cookie", "Wilma2SID=sid; path=/"),
+            )
+            .with_response(
+                Method::Get,
+                "https://wilma.example.com",
+                Response::new(200, r#"<a class="role" href="/!0123456/">Oppilas</a>"#),
+            );
+
+        let overrides = SelectorOverrides::from_json(
+            r#"{ "versions": { "13": { "role": { "selector": "a.role", "attribute": "href" } } } }"#,
+        )
+        .unwrap();
+
+        let credentials = Credentials {
+            username: "user",
+            password: "hunter2",
+            server: "wilma.example.com",
+        };
+
+        let options = LoginOptions {
+            overrides,
+            ..Default::default()
+        };
+
+        let client = match Client::start_login_with_options(transport, credentials, &options)
+            .await
+            .unwrap()
+        {
+            Login::Complete(client) => client,
+            Login::MfaRequired(_) => panic!("the login shouldn't require a code"),
+        };
+
+        assert_eq!(client.base_url, "https://wilma.example.com/!0123456/");
+        assert_eq!(client.selectors.rule("role").unwrap().selector, "a.role");
+    }
+
+    #[tokio::test]
+    async fn logs_in_with_a_language() {
+        let transport = Arc::new(
+            MemoryTransport::new()
+                .with_response(
+                    Method::Get,
+                    "https://wilma.example.com/index_json",
+                    Response::new(
+                        200,
+                        r#"{ "LoginResult": "Failed", "SessionID": "abc", "ApiVersion": 13 }"#,
+                    ),
+                )
+                .with_response(
+                    Method::Post,
+                    "https://wilma.example.com/login?langid=2",
+                    Response::new(303, "").with_header("Set-Cookie", "Wilma2SID=sid; path=/"),
+                )
+                .with_response(
+                    Method::Get,
+                    "https://wilma.example.com?langid=2",
+                    Response::new(
+                        200,
+                        r#"<a class="text-style-link" href="/!0123456/">Elev</a>"#,
+                    ),
+                ),
+        );
+
+        let credentials = Credentials {
+            username: "user",
+            password: "hunter2",
+            server: "wilma.example.com",
+        };
+
+        let options = LoginOptions {
+            role: Some("elev".into()),
+            language: Some(Language::Swedish),
+            ..Default::default()
+        };
+
+        let client =
+            match Client::start_login_with_options(transport.clone(), credentials, &options)
+                .await
+                .unwrap()
+            {
+                Login::Complete(client) => client,
+                Login::MfaRequired(_) => panic!("the login shouldn't require a code"),
+            };
+
+        assert_eq!(client.base_url, "https://wilma.example.com/!0123456/");
+        assert_eq!(client.language(), Some(Language::Swedish));
+        assert_eq!(transport.requests().len(), 3);
+    }
+
+    #[tokio::test]
+    async fn rejects_invalid_credentials() {
+        let transport = MemoryTransport::new().with_response(
+            Method::Get,
+            "https://wilma.example.com/index_json",
+            Response::new(
+                200,
+                r#"{ "LoginResult": "Failed", "SessionID": "abc", "ApiVersion": 13 }"#,
+            ),
+        );
+
+        let credentials = Credentials {
+            username: "user",
+            password: "wrong",
+            server: "wilma.example.com",
+        };
+
+        let error = Client::login_with(transport, credentials)
+            .await
+            .err()
+            .unwrap();
+        assert!(matches!(
+            error.downcast_ref::<WilmaError>(),
+            Some(WilmaError::InvalidCredentials)
+        ));
+    }
+
+    #[tokio::test]
+    async fn revalidates_with_etag() {
+        let url = format!("{}overview", BASE_URL);
+        let transport = MemoryTransport::new()
+            .with_response(
+                Method::Get,
+                &url,
+                Response::new(200, "hello").with_header("ETag", "\"v1\""),
+            )
+            .with_response(Method::Get, &url, Response::new(304, ""));
+
+        let client = client(transport, Duration::ZERO);
+
+        assert_eq!(client.fetch(&url).await.unwrap().1, "hello");
+        assert_eq!(client.fetch(&url).await.unwrap(), (200, "hello".into()));
+
+        let requests = client.transport().requests();
+        assert_eq!(header(&requests[0], "If-None-Match"), None);
+        assert_eq!(header(&requests[1], "If-None-Match"), Some("\"v1\""));
+        assert_eq!(header(&requests[1], "Cookie"), Some("Wilma2SID=session"));
+    }
+
+    #[tokio::test]
+    async fn fresh_responses_skip_the_server() {
+        let url = format!("{}overview", BASE_URL);
+        let transport =
+            MemoryTransport::new().with_response(Method::Get, &url, Response::new(200, "hello"));
+
+        let client = client(transport, Duration::from_secs(60));
+
+        client.fetch(&url).await.unwrap();
+        assert_eq!(client.fetch(&url).await.unwrap().1, "hello");
+        assert_eq!(client.transport().requests().len(), 1);
+
+        client.invalidate("overview");
+        assert!(client.cache.as_ref().unwrap().backend().get(&url).is_none());
+    }
+
+    const SCHEDULE: &str = r#"{
+        "Terms": [],
+        "Schedule": [{
+            "Day": 1, "Class": "7A", "Color": null, "Start": "08:15", "End": "09:30",
+            "ScheduleID": 1, "ReservationID": 1,
+            "Groups": [{
+                "Caption": "MA", "Class": "7A", "CourseId": 1, "FullCaption": "Matematiikka",
+                "Id": 1, "ShortCaption": "MA",
+                "Rooms": [
+                    { "Caption": "101", "Id": 101, "LongCaption": "Luokka 101",
+                      "ScheduleVisible": true },
+                    { "Caption": "102", "Id": 102, "LongCaption": "Luokka 102",
+                      "ScheduleVisible": false }
+                ],
+                "Teachers": [
+                    { "Caption": "MM", "Id": 7, "LongCaption": "Meikäläinen Maija",
+                      "ScheduleVisible": true }
+                ]
+            }]
+        }]
+    }"#;
+
+    #[tokio::test]
+    async fn gets_room_and_teacher_schedules() {
+        let transport = MemoryTransport::new()
+            .with_response(
+                Method::Get,
+                format!("{}schedule/export/rooms/101", BASE_URL),
+                Response::new(200, SCHEDULE),
+            )
+            .with_response(
+                Method::Get,
+                format!("{}schedule/export/teachers/7", BASE_URL),
+                Response::new(200, SCHEDULE),
+            )
+            .with_response(
+                Method::Get,
+                format!("{}schedule/export/rooms/103", BASE_URL),
+                Response::new(403, ""),
+            );
+
+        let client = client(transport, Duration::ZERO);
+        let schedule = client.get_room_schedule(101).await.unwrap();
+
+        let rooms: Vec<u32> = schedule.rooms().iter().map(|r| r.id()).collect();
+        assert_eq!(rooms, [101]);
+        let teachers: Vec<u32> = schedule.teachers().iter().map(|t| t.id).collect();
+        assert_eq!(teachers, [7]);
+
+        let teacher = schedule.teachers()[0];
+        assert!(client.get_schedule_of_teacher(teacher).await.is_ok());
+
+        let error = client.get_room_schedule(103).await.err().unwrap();
+        assert!(matches!(
+            error.downcast_ref::<WilmaError>(),
+            Some(WilmaError::ScheduleNotVisible)
+        ));
+
+        // Other errors aren't mistaken for hidden schedules.
+        let error = client.get_teacher_schedule(8).await.err().unwrap();
+        assert!(error.downcast_ref::<WilmaError>().is_none());
+        assert!(error.to_string().contains("404"));
+    }
+
+    #[tokio::test]
+    async fn skips_hidden_schedules() {
+        let schedule: Schedule = serde_json::from_str(SCHEDULE).unwrap();
+        let hidden = schedule.reservations()[0].groups[0].rooms()[1].clone();
+
+        let client = client(MemoryTransport::new(), Duration::ZERO);
+        let error = client.get_schedule_of_room(&hidden).await.err().unwrap();
+
+        assert!(matches!(
+            error.downcast_ref::<WilmaError>(),
+            Some(WilmaError::ScheduleNotVisible)
+        ));
+        assert!(client.transport().requests().is_empty());
+    }
+
+    #[tokio::test]
+    async fn reports_error_statuses() {
+        let transport = MemoryTransport::new().with_response(
+            Method::Get,
+            format!("{}messages/list", BASE_URL),
+            Response::new(404, "<html>Not found</html>"),
+        );
+
+        let client = client(transport, Duration::ZERO);
+
+        let error = client.get_messages().await.unwrap_err();
+        assert!(error.to_string().contains("status 404"), "{}", error);
+        assert!(error.downcast_ref::<WilmaError>().is_none());
+
+        let error = client.get_user_profile().await.unwrap_err();
+        assert!(error.to_string().contains("status 404"), "{}", error);
+    }
+
+    #[cfg(feature = "unstable-week-schedule")]
+    #[tokio::test]
+    async fn gets_the_schedule_of_a_week() {
+        let schedule_url = format!("{}schedule/export/students/123456", BASE_URL);
+        let transport = MemoryTransport::new()
+            .with_response(
+                Method::Get,
+                BASE_URL,
+                Response::new(200, include_str!("parser/fixtures/student_front_page.html")),
+            )
+            .with_response(
+                Method::Get,
+                format!("{}?date=2022-10-19", schedule_url),
+                Response::new(200, include_str!("wilma/schedule/fixtures/week.json")),
+            );
+
+        let client = client(transport, Duration::ZERO);
+        let date = NaiveDate::from_ymd_opt(2022, 10, 19).unwrap();
+        let week = client.get_user_week_schedule(date).await.unwrap();
+
+        assert!(week.reservations()[1].cancelled);
+    }
+
+    #[tokio::test]
+    async fn requests_the_language() {
+        let url = format!("{}overview", BASE_URL);
+        let transport = MemoryTransport::new()
+            .with_response(Method::Get, &url, Response::new(200, "hei"))
+            .with_response(
+                Method::Get,
+                format!("{}?langid=2", url),
+                Response::new(200, "hej"),
+            );
+
+        let client = client(transport, Duration::from_secs(60));
+        assert_eq!(client.fetch(&url).await.unwrap().1, "hei");
 
-        let debug_json: Value = serde_json::from_str(&response).unwrap();
+        // The cached response of the default language isn't returned in another language.
+        let client = client.with_language(Language::Swedish);
+        assert_eq!(client.fetch(&url).await.unwrap().1, "hej");
+        assert_eq!(client.fetch(&url).await.unwrap().1, "hej");
 
-        println!("{}", serde_json::to_string_pretty(&debug_json).unwrap());
+        let requests = client.transport().requests();
+        assert_eq!(requests.len(), 2);
+        assert_eq!(requests[1].url, format!("{}?langid=2", url));
 
-        Ok(serde_json::from_str(&response)?)
+        client.invalidate("overview");
+        client.fetch(&url).await.unwrap();
+        assert_eq!(client.transport().requests().len(), 3);
     }
 }
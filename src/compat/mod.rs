@@ -0,0 +1,318 @@
+//! Differences between Wilma API versions.
+//!
+//! Wilma reports its API version in `index_json`. Older versions can serve endpoints from other
+//! paths, name JSON fields differently and use other markup, so the client looks up the path,
+//! the field names and the selectors for the version of the server it talks to. Responses are
+//! normalized to the field names of the latest version before they're deserialized.
+//!
+//! No differences are confirmed from captured responses yet, so for now every supported version
+//! is handled like the latest one, and only versions older than `ApiVersion::OLDEST` are
+//! rejected.
+
+use crate::{parser::Selectors, Error as WilmaError};
+use serde_json::Value;
+use std::fmt;
+
+/// The JSON endpoints whose path or fields differ between versions.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Endpoint {
+    Overview,
+    Messages,
+    News,
+    Notes,
+    Grades,
+}
+
+impl Endpoint {
+    /// The path of the endpoint in the latest version, relative to the base URL.
+    pub fn path(&self) -> &'static str {
+        use Endpoint::*;
+
+        match self {
+            Overview => "overview",
+            Messages => "messages/list",
+            News => "news/list",
+            Notes => "attendance/notes",
+            Grades => "grades/list",
+        }
+    }
+
+    /// The key of the list in the response. `Overview` isn't a list.
+    pub fn list_key(&self) -> Option<&'static str> {
+        use Endpoint::*;
+
+        match self {
+            Overview => None,
+            Messages => Some("Messages"),
+            News => Some("News"),
+            Notes => Some("Notes"),
+            Grades => Some("Grades"),
+        }
+    }
+}
+
+/// How an endpoint looked in versions up to and including `until`.
+struct Difference {
+    until: u32,
+    endpoint: Endpoint,
+    path: Option<&'static str>,
+
+    /// Old names and the names they have in the latest version. They're applied to the response
+    /// and to every item of its list.
+    renames: &'static [(&'static str, &'static str)],
+}
+
+/// The differences, ordered from the newest version to the oldest. An entry has to be backed by
+/// a response captured from a server running the version it describes. None are confirmed yet,
+/// so every supported version is talked to like the latest one.
+const DIFFERENCES: &[Difference] = &[];
+
+/// A Wilma API version the client knows how to talk to.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct ApiVersion(u32);
+
+impl ApiVersion {
+    /// The oldest supported version.
+    pub const OLDEST: Self = Self(11);
+
+    /// The latest version the client was tested against. Newer versions are assumed to work
+    /// like it.
+    pub const LATEST: Self = Self(13);
+
+    /// Fails with `Error::UnsupportedApiVersion` for versions older than `ApiVersion::OLDEST`.
+    pub fn new(version: u32) -> anyhow::Result<Self> {
+        if version < Self::OLDEST.0 {
+            return Err(
+                anyhow::Error::from(WilmaError::UnsupportedApiVersion).context(format!(
+                    "Wilma API version {} is older than the oldest supported version {}.",
+                    version,
+                    Self::OLDEST
+                )),
+            );
+        }
+
+        if version > Self::LATEST.0 {
+            tracing::warn!(version, "newer Wilma API version than the latest known one");
+        }
+
+        Ok(Self(version))
+    }
+
+    pub fn number(&self) -> u32 {
+        self.0
+    }
+
+    /// The path of an endpoint relative to the base URL.
+    pub fn path(&self, endpoint: Endpoint) -> &'static str {
+        self.path_in(DIFFERENCES, endpoint)
+    }
+
+    /// Renames the fields of a response of an endpoint to their names in the latest version.
+    pub fn normalize(&self, endpoint: Endpoint, json: &mut Value) {
+        self.normalize_in(DIFFERENCES, endpoint, json)
+    }
+
+    fn path_in(&self, differences: &'static [Difference], endpoint: Endpoint) -> &'static str {
+        self.differences(differences, endpoint)
+            .filter_map(|d| d.path)
+            .last()
+            .unwrap_or_else(|| endpoint.path())
+    }
+
+    /// The built-in selectors for the markup of this version.
+    pub fn selectors(&self) -> Selectors {
+        Selectors::for_version(self.0)
+    }
+
+    fn normalize_in(
+        &self,
+        differences: &'static [Difference],
+        endpoint: Endpoint,
+        json: &mut Value,
+    ) {
+        for difference in self.differences(differences, endpoint) {
+            rename(json, difference.renames);
+
+            if let Some(Value::Array(items)) = endpoint.list_key().and_then(|k| json.get_mut(k)) {
+                for item in items {
+                    rename(item, difference.renames);
+                }
+            }
+        }
+    }
+
+    /// The differences of a table that apply to this version, from the newest to the oldest.
+    fn differences(
+        &self,
+        differences: &'static [Difference],
+        endpoint: Endpoint,
+    ) -> impl Iterator<Item = &'static Difference> + '_ {
+        differences
+            .iter()
+            .filter(move |d| d.endpoint == endpoint && self.0 <= d.until)
+    }
+}
+
+impl Default for ApiVersion {
+    fn default() -> Self {
+        Self::LATEST
+    }
+}
+
+impl fmt::Display for ApiVersion {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}", self.0)
+    }
+}
+
+fn rename(json: &mut Value, renames: &[(&str, &str)]) {
+    let object = match json.as_object_mut() {
+        Some(object) => object,
+        None => return,
+    };
+
+    for (old, new) in renames {
+        if let Some(value) = object.remove(*old) {
+            object.entry(*new).or_insert(value);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        transport::{MemoryTransport, Method, Response},
+        Client, Credentials, Secret, Session,
+    };
+    use std::sync::Arc;
+
+    const BASE_URL: &str = "https://wilma.example.com/!0123456/";
+
+    #[tokio::test]
+    async fn parses_the_latest_version() {
+        let api_version = ApiVersion::LATEST;
+        let mut transport = MemoryTransport::new().with_response(
+            Method::Get,
+            BASE_URL,
+            Response::new(
+                200,
+                include_str!("../parser/fixtures/student_front_page.html"),
+            ),
+        );
+
+        for (endpoint, body) in [
+            (
+                Endpoint::Messages,
+                include_str!("fixtures/13/messages.json"),
+            ),
+            (Endpoint::News, include_str!("fixtures/13/news.json")),
+            (Endpoint::Grades, include_str!("fixtures/13/grades.json")),
+        ] {
+            let url = format!("{}{}", BASE_URL, api_version.path(endpoint));
+            transport = transport.with_response(Method::Get, url, Response::new(200, body));
+        }
+
+        let session = Session {
+            base_url: BASE_URL.into(),
+            id: Secret::new("session"),
+        };
+        let client = Client::resume_with(transport, session)
+            .with_api_version(api_version.number())
+            .unwrap();
+
+        let user = client.get_user_profile().await.unwrap();
+        assert_eq!(user.name(), "Meikäläinen Matti");
+        assert_eq!(user.school(), "Esimerkkikoulu");
+        assert_eq!(user.user_id(), "123456");
+
+        let messages = client.get_messages().await.unwrap();
+        assert_eq!(messages.len(), 2);
+        assert_eq!(messages[0].timestamp, "2022-03-14 12:30");
+        assert!(messages[0].is_unread());
+
+        let news = client.get_news().await.unwrap();
+        assert_eq!(news[0].published, "2022-10-10");
+
+        let grades = client.get_grades().await.unwrap();
+        assert_eq!(grades[0].course_name, "Matematiikka");
+        assert_eq!(grades[0].grade, "9");
+    }
+
+    #[test]
+    fn rejects_versions_older_than_the_oldest() {
+        for version in [0, 9, 10] {
+            let error = ApiVersion::new(version).unwrap_err();
+            assert!(matches!(
+                error.downcast_ref::<WilmaError>(),
+                Some(WilmaError::UnsupportedApiVersion)
+            ));
+        }
+
+        assert_eq!(ApiVersion::new(11).unwrap(), ApiVersion::OLDEST);
+    }
+
+    #[tokio::test]
+    async fn rejects_unsupported_versions() {
+        let transport = Arc::new(MemoryTransport::new().with_response(
+            Method::Get,
+            "https://wilma.example.com/index_json",
+            Response::new(
+                200,
+                r#"{ "LoginResult": "Failed", "SessionID": "abc", "ApiVersion": 9 }"#,
+            ),
+        ));
+
+        let credentials = Credentials {
+            username: "user",
+            password: "hunter2",
+            server: "wilma.example.com",
+        };
+
+        let error = Client::login_with(transport.clone(), credentials)
+            .await
+            .err()
+            .unwrap();
+        assert!(matches!(
+            error.downcast_ref::<WilmaError>(),
+            Some(WilmaError::UnsupportedApiVersion)
+        ));
+
+        // The password isn't sent to a server the client can't talk to.
+        assert_eq!(transport.requests().len(), 1);
+    }
+
+    /// A made-up table, for testing the lookup rather than any real server.
+    const EXAMPLE: &[Difference] = &[
+        Difference {
+            until: 12,
+            endpoint: Endpoint::Grades,
+            path: Some("grades/old"),
+            renames: &[("Courses", "Grades"), ("Course", "CourseName")],
+        },
+        Difference {
+            until: 11,
+            endpoint: Endpoint::Grades,
+            path: Some("grades/oldest"),
+            renames: &[],
+        },
+    ];
+
+    #[test]
+    fn picks_paths_and_fields_by_version() {
+        let oldest = ApiVersion::new(11).unwrap();
+        assert_eq!(oldest.path_in(EXAMPLE, Endpoint::Grades), "grades/oldest");
+        assert_eq!(oldest.path_in(EXAMPLE, Endpoint::Notes), "attendance/notes");
+
+        let old = ApiVersion::new(12).unwrap();
+        assert_eq!(old.path_in(EXAMPLE, Endpoint::Grades), "grades/old");
+
+        let mut json = serde_json::json!({ "Courses": [{ "Course": "Matematiikka" }] });
+        old.normalize_in(EXAMPLE, Endpoint::Grades, &mut json);
+        assert_eq!(json["Grades"][0]["CourseName"], "Matematiikka");
+
+        let newer = ApiVersion::new(14).unwrap();
+        assert_eq!(newer.path_in(EXAMPLE, Endpoint::Grades), "grades/list");
+        assert_eq!(newer.path(Endpoint::Messages), "messages/list");
+    }
+}
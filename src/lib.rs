@@ -1,37 +1,71 @@
-use std::env;
 use std::error::Error as StdError;
 use std::fmt;
 
+pub mod accounts;
+pub mod availability;
+#[cfg(feature = "blocking")]
+pub mod blocking;
+pub mod cache;
 pub mod client;
-pub use client::{Client, Credentials};
+pub use client::{Client, Credentials, Session};
+pub mod compat;
+pub use compat::ApiVersion;
 
+pub mod login;
+pub use login::{Login, LoginOptions, MfaRejected, PendingLogin, Sso, SsoLogin, SsoStep};
+
+pub mod locale;
+pub use locale::{Language, Localize};
+pub mod notify;
 pub mod parser;
+pub mod policy;
+pub mod probe;
+#[cfg(feature = "reqwest")]
+pub use probe::probe;
+pub use probe::ServerInfo;
+pub mod redact;
+pub mod rooms;
+pub mod runtime;
+pub mod secret;
+pub use secret::{OwnedCredentials, Secret};
+#[cfg(feature = "store")]
+pub mod store;
+#[cfg(feature = "totp")]
+pub mod totp;
+pub mod transport;
 pub mod utils;
+#[cfg(feature = "vault")]
+pub mod vault;
+pub mod watcher;
 pub mod wilma;
 
 /// The Error enum. Used for handling Wilma-specific errors.
 #[derive(Debug)]
 pub enum Error {
     InvalidCredentials,
+    ScheduleNotVisible,
+    SessionExpired,
+    RoleNotFound,
+    InvalidPassphrase,
+    MfaRequired,
+    InvalidMfaCode,
+    UnexpectedPage,
+    UnsupportedApiVersion,
 }
 
 impl fmt::Display for Error {
     fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
-        use Error::*;
-
-        let msg = match self {
-            InvalidCredentials => "Invalid credentials were provided.",
-        };
-
-        write!(f, "{}", msg)
+        write!(f, "{}", self.localize(Language::English))
     }
 }
 
 impl StdError for Error {}
 
-#[cfg(test)]
+#[cfg(all(test, feature = "reqwest"))]
 mod tests {
     use super::*;
+    use std::env;
+
     #[test]
     fn it_works() {
         assert_eq!(2 + 2, 4);
@@ -0,0 +1,278 @@
+//! Finnish, Swedish and English names of the values shown to users.
+//!
+//! Wilma itself can also send its pages in these languages, selected with the `langid` parameter;
+//! see `Client::with_language`.
+
+use crate::{
+    wilma::{note::NoteType, schedule::reservation::Weekday, user::UserType, Grade},
+    Error as WilmaError,
+};
+use serde::{Deserialize, Serialize};
+use std::fmt;
+use std::str::FromStr;
+
+/// A language Wilma and the crate are available in.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub enum Language {
+    Finnish,
+    Swedish,
+    #[default]
+    English,
+}
+
+impl Language {
+    /// The `langid` Wilma selects the language of its pages with, as in `/?langid=2`.
+    pub fn id(&self) -> u32 {
+        use Language::*;
+
+        match self {
+            Finnish => 1,
+            Swedish => 2,
+            English => 3,
+        }
+    }
+
+    /// The ISO 639-1 code of the language, as used by Wilma.
+    pub fn code(&self) -> &'static str {
+        use Language::*;
+
+        match self {
+            Finnish => "fi",
+            Swedish => "sv",
+            English => "en",
+        }
+    }
+}
+
+impl FromStr for Language {
+    type Err = anyhow::Error;
+
+    /// Parses a language code like `fi` or a language tag like `sv-FI`.
+    fn from_str(code: &str) -> anyhow::Result<Self> {
+        let language = code.split(['-', '_']).next().unwrap_or_default();
+
+        match language.to_ascii_lowercase().as_str() {
+            "fi" => Ok(Language::Finnish),
+            "sv" => Ok(Language::Swedish),
+            "en" => Ok(Language::English),
+            _ => anyhow::bail!("Unsupported language {:?}.", code),
+        }
+    }
+}
+
+impl fmt::Display for Language {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}", self.code())
+    }
+}
+
+/// Adds the `langid` parameter of a language to a URL. Without a language, the URL is returned
+/// as it is and Wilma uses the default language of the server.
+pub(crate) fn with_langid(url: &str, language: Option<Language>) -> String {
+    match language {
+        Some(language) => {
+            let separator = if url.contains('?') { '&' } else { '?' };
+            format!("{}{}langid={}", url, separator, language.id())
+        }
+        None => url.to_string(),
+    }
+}
+
+/// A value with a name in every `Language`.
+pub trait Localize {
+    fn localize(&self, language: Language) -> &str;
+}
+
+/// Picks the Finnish, Swedish or English text.
+fn pick(language: Language, [fi, sv, en]: [&str; 3]) -> &str {
+    match language {
+        Language::Finnish => fi,
+        Language::Swedish => sv,
+        Language::English => en,
+    }
+}
+
+impl Localize for Weekday {
+    fn localize(&self, language: Language) -> &str {
+        use Weekday::*;
+
+        let names = match self {
+            Monday => ["Maanantai", "Måndag", "Monday"],
+            Tuesday => ["Tiistai", "Tisdag", "Tuesday"],
+            Wednesday => ["Keskiviikko", "Onsdag", "Wednesday"],
+            Thursday => ["Torstai", "Torsdag", "Thursday"],
+            Friday => ["Perjantai", "Fredag", "Friday"],
+            Saturday => ["Lauantai", "Lördag", "Saturday"],
+            Sunday => ["Sunnuntai", "Söndag", "Sunday"],
+        };
+
+        pick(language, names)
+    }
+}
+
+impl Localize for UserType {
+    fn localize(&self, language: Language) -> &str {
+        use UserType::*;
+
+        let names = match self {
+            Teacher => ["Opettaja", "Lärare", "Teacher"],
+            Student => ["Opiskelija", "Studerande", "Student"],
+            Personnel => ["Henkilökunta", "Personal", "Personnel"],
+            Guardian => ["Huoltaja", "Vårdnadshavare", "Guardian"],
+            Instructor => [
+                "Työpaikkaohjaaja",
+                "Arbetsplatshandledare",
+                "Workplace instructor",
+            ],
+            Management => ["Johto", "Ledning", "Management"],
+            Passwd => ["Wilma-tunnus", "Wilma-konto", "Wilma account"],
+            Unknown => ["Tuntematon", "Okänd", "Unknown"],
+        };
+
+        pick(language, names)
+    }
+}
+
+impl Localize for NoteType {
+    fn localize(&self, language: Language) -> &str {
+        use NoteType::*;
+
+        let names = match self {
+            Absence => [
+                "Selvittämätön poissaolo",
+                "Outredd frånvaro",
+                "Unexcused absence",
+            ],
+            ExcusedAbsence => ["Selvitetty poissaolo", "Utredd frånvaro", "Excused absence"],
+            Late => ["Myöhästyminen", "Försening", "Late"],
+            Forgotten => [
+                "Unohtuneet kotitehtävät tai välineet",
+                "Glömda läxor eller redskap",
+                "Forgotten homework or equipment",
+            ],
+            Praise => ["Positiivinen palaute", "Positiv respons", "Praise"],
+            Warning => [
+                "Häiritsevä käytös",
+                "Störande beteende",
+                "Disturbing behaviour",
+            ],
+            Unknown => ["Tuntematon", "Okänd", "Unknown"],
+        };
+
+        pick(language, names)
+    }
+}
+
+impl Localize for Grade {
+    /// The meaning of a letter grade, like "Passed" for `S`. Numeric grades are returned as they
+    /// are.
+    fn localize(&self, language: Language) -> &str {
+        let names = match self.grade.trim() {
+            "S" => ["Suoritettu", "Godkänd", "Passed"],
+            "H" => ["Hylätty", "Underkänd", "Failed"],
+            "O" => ["Osallistunut", "Deltagit", "Participated"],
+            _ => return &self.grade,
+        };
+
+        pick(language, names)
+    }
+}
+
+impl Localize for WilmaError {
+    fn localize(&self, language: Language) -> &str {
+        use WilmaError::*;
+
+        let messages = match self {
+            InvalidCredentials => [
+                "Käyttäjätunnus tai salasana on väärä.",
+                "Användarnamnet eller lösenordet är fel.",
+                "Invalid credentials were provided.",
+            ],
+            ScheduleNotVisible => [
+                "Pyydetty lukujärjestys ei näy käyttäjälle.",
+                "Det begärda schemat syns inte för användaren.",
+                "The requested schedule is not visible to the user.",
+            ],
+            SessionExpired => [
+                "Istunto on vanhentunut, kirjaudu uudelleen.",
+                "Sessionen har gått ut, logga in igen.",
+                "The session has expired, log in again.",
+            ],
+            RoleNotFound => [
+                "Tunnuksella ei ole pyydettyä roolia.",
+                "Kontot har inte den begärda rollen.",
+                "The account doesn't have the requested role.",
+            ],
+            InvalidPassphrase => [
+                "Holvin tunnuslause on väärä tai holvi on vioittunut.",
+                "Valvets lösenfras är fel eller valvet är skadat.",
+                "The vault passphrase is wrong, or the vault is corrupted.",
+            ],
+            MfaRequired => [
+                "Tunnus vaatii kaksivaiheisen tunnistautumisen.",
+                "Kontot kräver tvåstegsverifiering.",
+                "The account requires two-step verification.",
+            ],
+            InvalidMfaCode => [
+                "Vahvistuskoodia ei hyväksytty.",
+                "Verifieringskoden godkändes inte.",
+                "The verification code was not accepted.",
+            ],
+            UnexpectedPage => [
+                "Wilma lähetti sivun, jota ei voitu lukea.",
+                "Wilma skickade en sida som inte kunde läsas.",
+                "Wilma sent a page that couldn't be parsed.",
+            ],
+            UnsupportedApiVersion => [
+                "Palvelin käyttää Wilman API-versiota, jota ei tueta.",
+                "Servern använder en version av Wilmas API som inte stöds.",
+                "The server uses a Wilma API version that isn't supported.",
+            ],
+        };
+
+        pick(language, messages)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parses_language_tags() {
+        assert_eq!("fi".parse::<Language>().unwrap(), Language::Finnish);
+        assert_eq!("sv-FI".parse::<Language>().unwrap(), Language::Swedish);
+        assert_eq!("EN_us".parse::<Language>().unwrap(), Language::English);
+        assert!("de".parse::<Language>().is_err());
+    }
+
+    #[test]
+    fn adds_the_langid() {
+        let url = "https://wilma.example.com/schedule?date=2022-10-17";
+        assert_eq!(
+            with_langid(url, Some(Language::Swedish)),
+            "https://wilma.example.com/schedule?date=2022-10-17&langid=2"
+        );
+        assert_eq!(
+            with_langid("https://wilma.example.com", Some(Language::Finnish)),
+            "https://wilma.example.com?langid=1"
+        );
+        assert_eq!(with_langid(url, None), url);
+    }
+
+    #[test]
+    fn localizes_values() {
+        assert_eq!(Weekday::Friday.localize(Language::Swedish), "Fredag");
+        assert_eq!(NoteType::Late.localize(Language::Finnish), "Myöhästyminen");
+        assert_eq!(
+            WilmaError::SessionExpired.localize(Language::Finnish),
+            "Istunto on vanhentunut, kirjaudu uudelleen."
+        );
+
+        // English is what `Display` shows.
+        assert_eq!(
+            WilmaError::SessionExpired.to_string(),
+            WilmaError::SessionExpired.localize(Language::English)
+        );
+    }
+}
@@ -0,0 +1,761 @@
+//! Logins that take more than one step, like two-step verification and single sign-on through
+//! an external identity provider.
+
+use crate::{
+    client::send,
+    compat::ApiVersion,
+    parser::{
+        core::{parse_forms, Form},
+        Page, SelectorOverrides, Selectors,
+    },
+    policy::{PolicyLayer, RequestPolicy},
+    runtime,
+    transport::{CookieJar, DefaultTransport, Request, Response, Transport},
+    utils, Client, Error as WilmaError, Language,
+};
+use std::error::Error as StdError;
+use std::fmt;
+use std::sync::Arc;
+use url::Url;
+
+/// How many redirects and automatically submitted forms a single sign-on step may go through.
+pub const MAX_REDIRECTS: usize = 20;
+
+/// Options for `Client::start_login_with_options` and `Client::start_sso_with_options`.
+#[derive(Debug, Clone, Default)]
+pub struct LoginOptions {
+    /// The role to log in as, by its identity or name. See `Client::login_as`.
+    pub role: Option<String>,
+
+    /// Overrides for parsing Wilma's pages. They're also applied to the client.
+    pub overrides: SelectorOverrides,
+
+    /// The language Wilma sends its pages in during the login. It's also set on the client with
+    /// `Client::with_language`.
+    pub language: Option<Language>,
+}
+
+/// The outcome of starting a login.
+pub enum Login<T: Transport = DefaultTransport> {
+    /// The login is done.
+    Complete(Client<T>),
+
+    /// The account uses two-step verification, and the login has to be completed with a code.
+    MfaRequired(PendingLogin<T>),
+}
+
+/// A login waiting for a two-step verification code.
+pub struct PendingLogin<T: Transport = DefaultTransport> {
+    flow: LoginFlow<T>,
+    form: Form,
+    form_url: String,
+}
+
+impl<T: Transport> PendingLogin<T> {
+    /// The verification form Wilma sent.
+    pub fn form(&self) -> &Form {
+        &self.form
+    }
+
+    /// Completes the login with a verification code, for example from an authenticator app.
+    /// If the code isn't accepted, the error is `Error::InvalidMfaCode` and the login can be
+    /// retried with another code; see `MfaRejected`.
+    #[tracing::instrument(name = "mfa", skip_all)]
+    pub async fn complete(mut self, code: &str) -> Result<Client<T>, MfaRejected<T>> {
+        let code_field = match code_field(&self.form) {
+            Some(field) => field.to_string(),
+            None => {
+                let error = anyhow::anyhow!("The verification form has no field for the code.");
+                return Err(MfaRejected::retry(error, self));
+            }
+        };
+
+        let mut fields: Vec<(&str, &str)> = self
+            .form
+            .hidden
+            .iter()
+            .map(|(name, value)| (name.as_str(), value.as_str()))
+            .collect();
+        fields.push((&code_field, code));
+
+        let request = self
+            .flow
+            .cookies
+            .apply(Request::post_form(&self.form_url, &fields));
+        let response = match send(&self.flow.transport, &self.flow.policy, request).await {
+            Ok(response) => response,
+            Err(error) => return Err(MfaRejected::retry(error, self)),
+        };
+        self.flow.cookies.store(&self.form_url, &response);
+
+        if let Some(session) = response.cookie("Wilma2SID") {
+            let session = session.to_string();
+
+            return Client::from_login(self.flow, session)
+                .await
+                .map_err(|error| MfaRejected {
+                    error,
+                    pending: None,
+                });
+        }
+
+        // Wilma sends the form again, with a new formkey, when the code is wrong.
+        match self.flow.mfa_form(&self.form_url, &response).await {
+            Ok(Some((form_url, form))) => {
+                self.form_url = form_url;
+                self.form = form;
+            }
+            Ok(None) => {}
+            Err(error) => return Err(MfaRejected::retry(error, self)),
+        }
+
+        Err(MfaRejected::retry(WilmaError::InvalidMfaCode.into(), self))
+    }
+
+    /// Completes the login with a code generated from a stored TOTP secret.
+    #[cfg(feature = "totp")]
+    pub async fn complete_with_totp(
+        self,
+        totp: &crate::totp::Totp,
+    ) -> Result<Client<T>, MfaRejected<T>> {
+        self.complete(&totp.code()).await
+    }
+}
+
+/// A failed `PendingLogin::complete`. It converts into `anyhow::Error`, so it can be returned
+/// with `?` when the login doesn't need to be retried.
+pub struct MfaRejected<T: Transport = DefaultTransport> {
+    /// `Error::InvalidMfaCode` if the code wasn't accepted, otherwise what went wrong.
+    pub error: anyhow::Error,
+
+    /// The login, for retrying with another code. None if the code was accepted but the login
+    /// failed after that, for example because the role wasn't found.
+    pub pending: Option<Box<PendingLogin<T>>>,
+}
+
+impl<T: Transport> MfaRejected<T> {
+    fn retry(error: anyhow::Error, pending: PendingLogin<T>) -> Self {
+        Self {
+            error,
+            pending: Some(Box::new(pending)),
+        }
+    }
+}
+
+impl<T: Transport> fmt::Debug for MfaRejected<T> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_struct("MfaRejected")
+            .field("error", &self.error)
+            .field("pending", &self.pending.is_some())
+            .finish()
+    }
+}
+
+impl<T: Transport> fmt::Display for MfaRejected<T> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}", self.error)
+    }
+}
+
+impl<T: Transport> StdError for MfaRejected<T> {
+    fn source(&self) -> Option<&(dyn StdError + 'static)> {
+        self.error.source()
+    }
+}
+
+/// The field of a verification form the code goes in: one named like a code, or the only text
+/// field. Checkboxes like "trust this device" are never picked.
+fn code_field(form: &Form) -> Option<&str> {
+    const NAMES: [&str; 4] = ["code", "otp", "token", "pin"];
+
+    let fields = form.text_inputs();
+
+    fields
+        .iter()
+        .find(|name| {
+            let name = name.to_ascii_lowercase();
+            NAMES.iter().any(|n| name.contains(n))
+        })
+        .or(fields.first())
+        .copied()
+}
+
+/// The outcome of a single sign-on step.
+pub enum Sso<T: Transport = DefaultTransport> {
+    /// The identity provider sent the user back to Wilma, and the login is done.
+    Complete(Client<T>),
+
+    /// The user has to authorize the login at the identity provider.
+    Authorize(SsoLogin<T>),
+}
+
+/// What the user has to do at the identity provider.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum SsoStep {
+    /// Fill in a form, usually a username and a password. See `SsoLogin::submit`.
+    Form { url: String, form: Form },
+
+    /// Open the URL in a browser. The identity provider redirects back to Wilma when it's done,
+    /// and that callback URL is passed to `SsoLogin::complete_with_callback`.
+    Browser { url: String },
+}
+
+/// A single sign-on login waiting for the user.
+pub struct SsoLogin<T: Transport = DefaultTransport> {
+    flow: LoginFlow<T>,
+    step: SsoStep,
+}
+
+impl<T: Transport> SsoLogin<T> {
+    pub fn step(&self) -> &SsoStep {
+        &self.step
+    }
+
+    /// Submits the form of a `SsoStep::Form` step with the given values. Hidden fields are sent
+    /// as they are. If the identity provider rejects the values, the next step is usually the
+    /// same form again.
+    #[tracing::instrument(name = "sso_submit", skip_all)]
+    pub async fn submit(self, values: &[(&str, &str)]) -> anyhow::Result<Sso<T>> {
+        let (url, form) = match &self.step {
+            SsoStep::Form { url, form } => (url.clone(), form),
+            SsoStep::Browser { .. } => anyhow::bail!("The single sign-on step has no form."),
+        };
+
+        let mut fields: Vec<(&str, &str)> = form
+            .hidden
+            .iter()
+            .map(|(name, value)| (name.as_str(), value.as_str()))
+            .collect();
+        fields.extend_from_slice(values);
+
+        let request = form_request(&url, &form.method, &fields)?;
+        self.flow.follow(request).await
+    }
+
+    /// Completes a `SsoStep::Browser` step with the URL the identity provider redirected the
+    /// browser to.
+    #[tracing::instrument(name = "sso_callback", skip_all)]
+    pub async fn complete_with_callback(self, callback_url: &str) -> anyhow::Result<Sso<T>> {
+        self.flow.follow(Request::get(callback_url)).await
+    }
+}
+
+impl<T: Transport> Client<T> {
+    /// Starts a single sign-on login using the given transport. `start_url` is the URL of the
+    /// identity provider's login link on the Wilma server, absolute or relative to the server,
+    /// for example `/saml/login/1` (see `ServerInfo::sso_providers`). Redirects are followed and
+    /// automatically submitted forms are sent until the user has to do something.
+    pub async fn start_sso_with(
+        transport: T,
+        server: &str,
+        start_url: &str,
+        role: Option<&str>,
+    ) -> anyhow::Result<Sso<T>> {
+        let options = LoginOptions {
+            role: role.map(String::from),
+            ..Default::default()
+        };
+
+        Self::start_sso_with_options(transport, server, start_url, &options).await
+    }
+
+    /// Starts a single sign-on login like `Client::start_sso_with`, with a language and selector
+    /// overrides. See `LoginOptions`.
+    #[tracing::instrument(name = "sso", skip(transport, options))]
+    pub async fn start_sso_with_options(
+        transport: T,
+        server: &str,
+        start_url: &str,
+        options: &LoginOptions,
+    ) -> anyhow::Result<Sso<T>> {
+        let server_url = utils::verify_url(&server);
+        let start_url = resolve(&format!("{}/", server_url), start_url)?;
+
+        let flow = LoginFlow {
+            transport,
+            policy: PolicyLayer::new(RequestPolicy::default(), runtime::default_timer()),
+            server_url,
+            api_version: None,
+            role: options.role.clone(),
+            cookies: CookieJar::new(),
+            overrides: options.overrides.clone(),
+            language: options.language,
+        };
+
+        flow.follow(Request::get(start_url)).await
+    }
+}
+
+#[cfg(feature = "reqwest")]
+impl Client {
+    /// Starts a single sign-on login. See `Client::start_sso_with`.
+    pub async fn start_sso(
+        server: &str,
+        start_url: &str,
+        role: Option<&str>,
+    ) -> anyhow::Result<Sso> {
+        Self::start_sso_with(DefaultTransport::new()?, server, start_url, role).await
+    }
+}
+
+/// The state carried between the steps of a login.
+pub(crate) struct LoginFlow<T: Transport> {
+    pub transport: T,
+    pub policy: PolicyLayer,
+    pub server_url: String,
+
+    /// The API version from `index_json`, if the login went through it.
+    pub api_version: Option<ApiVersion>,
+    pub role: Option<String>,
+    pub cookies: CookieJar,
+    pub overrides: SelectorOverrides,
+    pub language: Option<Language>,
+}
+
+impl<T: Transport> LoginFlow<T> {
+    /// The selectors for the API version of the server, with the overrides applied.
+    pub fn selectors(&self) -> Arc<Selectors> {
+        Arc::new(self.overrides.resolve(self.api_version.map(|v| v.number())))
+    }
+
+    /// Handles the response to a login form: either Wilma set the session cookie, or it sent a
+    /// two-step verification form, possibly behind a redirect.
+    pub async fn finish(mut self, url: &str, response: Response) -> anyhow::Result<Login<T>> {
+        self.cookies.store(url, &response);
+
+        if let Some(session) = response.cookie("Wilma2SID") {
+            let session = session.to_string();
+            let client = Client::from_login(self, session).await?;
+
+            return Ok(Login::Complete(client));
+        }
+
+        let (form_url, form) = match self.mfa_form(url, &response).await? {
+            Some(form) => form,
+            None => {
+                tracing::warn!("no Wilma2SID cookie in the login response");
+                return Err(WilmaError::InvalidCredentials.into());
+            }
+        };
+
+        tracing::info!("two-step verification required");
+
+        Ok(Login::MfaRequired(PendingLogin {
+            form_url,
+            form,
+            flow: self,
+        }))
+    }
+
+    /// The two-step verification form in a response, possibly behind a redirect, and the URL
+    /// it's submitted to.
+    async fn mfa_form(
+        &mut self,
+        url: &str,
+        response: &Response,
+    ) -> anyhow::Result<Option<(String, Form)>> {
+        let (page_url, page) = match redirect(url, response) {
+            Some(location) => {
+                let request = self.cookies.apply(Request::get(&location));
+                let page = send(&self.transport, &self.policy, request).await?;
+                self.cookies.store(&location, &page);
+
+                (location, page.text())
+            }
+            None => (url.to_string(), response.text()),
+        };
+
+        match parse_forms(&Page::with_selectors(&page, self.selectors()))
+            .into_iter()
+            .find(is_mfa_form)
+        {
+            Some(form) => Ok(Some((resolve(&page_url, &form.action)?, form))),
+            None => Ok(None),
+        }
+    }
+}
+
+impl<T: Transport> LoginFlow<T> {
+    /// Sends a request and follows redirects and automatically submitted forms, keeping the
+    /// cookies of every host, until Wilma sets the session cookie or the user has to act.
+    async fn follow(mut self, mut request: Request) -> anyhow::Result<Sso<T>> {
+        let wilma_host = Url::parse(&self.server_url)?.host_str().map(String::from);
+
+        for _ in 0..MAX_REDIRECTS {
+            let url = request.url.clone();
+            let response = send(&self.transport, &self.policy, self.cookies.apply(request)).await?;
+            self.cookies.store(&url, &response);
+
+            let host = Url::parse(&url)?.host_str().map(String::from);
+
+            if let (true, Some(session)) = (host == wilma_host, response.cookie("Wilma2SID")) {
+                let session = session.to_string();
+                let client = Client::from_login(self, session).await?;
+
+                tracing::info!("single sign-on complete");
+                return Ok(Sso::Complete(client));
+            }
+
+            if let Some(location) = redirect(&url, &response) {
+                request = Request::get(location);
+                continue;
+            }
+
+            let forms = parse_forms(&Page::with_selectors(&response.text(), self.selectors()));
+
+            // SAML and OpenID Connect send the response back in a form that submits itself.
+            if let Some(form) = forms
+                .iter()
+                .find(|f| f.inputs.is_empty() && !f.hidden.is_empty())
+            {
+                let fields: Vec<(&str, &str)> = form
+                    .hidden
+                    .iter()
+                    .map(|(name, value)| (name.as_str(), value.as_str()))
+                    .collect();
+
+                request = form_request(&resolve(&url, &form.action)?, &form.method, &fields)?;
+                continue;
+            }
+
+            let step = match forms.into_iter().find(|f| !f.inputs.is_empty()) {
+                Some(form) => SsoStep::Form {
+                    url: resolve(&url, &form.action)?,
+                    form,
+                },
+                None => SsoStep::Browser { url },
+            };
+
+            return Ok(Sso::Authorize(SsoLogin { flow: self, step }));
+        }
+
+        anyhow::bail!("Too many redirects during single sign-on.")
+    }
+}
+
+/// A request submitting a form with the given method.
+fn form_request(url: &str, method: &str, fields: &[(&str, &str)]) -> anyhow::Result<Request> {
+    if method == "post" {
+        return Ok(Request::post_form(url, fields));
+    }
+
+    let mut url = Url::parse(url)?;
+    url.query_pairs_mut().extend_pairs(fields);
+
+    Ok(Request::get(url.to_string()))
+}
+
+/// Wilma's two-step verification form posts to an `mfa` or `otp` path.
+fn is_mfa_form(form: &Form) -> bool {
+    let action = form.action.to_ascii_lowercase();
+    action.contains("mfa") || action.contains("otp")
+}
+
+/// The absolute URL a redirect points to.
+pub(crate) fn redirect(url: &str, response: &Response) -> Option<String> {
+    if !(300..400).contains(&response.status) {
+        return None;
+    }
+
+    resolve(url, response.header("Location")?).ok()
+}
+
+/// Resolves a possibly relative URL against the URL of the page it appeared on.
+pub(crate) fn resolve(base: &str, url: &str) -> anyhow::Result<String> {
+    Ok(Url::parse(base)?.join(url)?.to_string())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::transport::{MemoryTransport, Method};
+    use crate::Credentials;
+
+    const SERVER: &str = "https://wilma.example.com";
+
+    fn wilma() -> MemoryTransport {
+        MemoryTransport::new()
+            .with_response(
+                Method::Get,
+                format!("{}/index_json", SERVER),
+                Response::new(
+                    200,
+                    r#"{ "LoginResult": "Failed", "SessionID": "abc", "ApiVersion": 13 }"#,
+                ),
+            )
+            .with_response(
+                Method::Post,
+                format!("{}/login", SERVER),
+                Response::new(303, "")
+                    .with_header("Location", "/mfa")
+                    .with_header("Set-Cookie", "Wilma2MfaID=pending; path=/"),
+            )
+            .with_response(
+                Method::Get,
+                format!("{}/mfa", SERVER),
+                Response::new(
+                    200,
+                    r#"<form method="post" action="/mfa/verify">
+                         <input type="hidden" name="formkey" value="mfa:1:abc" />
+                         <input type="text" name="OtpCode" />
+                         <input type="submit" value="Verify" />
+                       </form>"#,
+                ),
+            )
+            .with_response(
+                Method::Get,
+                SERVER,
+                Response::new(200, r#"<a class="text-style-link" href="/!01/">Anna</a>"#),
+            )
+    }
+
+    fn credentials() -> Credentials<'static> {
+        Credentials {
+            username: "user",
+            password: "hunter2",
+            server: "wilma.example.com",
+        }
+    }
+
+    #[tokio::test]
+    async fn completes_two_step_verification() {
+        let transport = wilma().with_response(
+            Method::Post,
+            format!("{}/mfa/verify", SERVER),
+            Response::new(303, "").with_header("Set-Cookie", "Wilma2SID=sid; path=/"),
+        );
+
+        let pending = match Client::start_login_with(transport, credentials(), None)
+            .await
+            .unwrap()
+        {
+            Login::MfaRequired(pending) => pending,
+            Login::Complete(_) => panic!("the login should require a code"),
+        };
+        assert_eq!(pending.form().inputs, vec!["OtpCode"]);
+
+        let client = pending.complete("123456").await.unwrap();
+        assert_eq!(client.session().base_url, format!("{}/!01/", SERVER));
+        assert_eq!(client.session().id.expose(), "sid");
+
+        let requests = client.transport().requests();
+        let verify = requests
+            .iter()
+            .find(|r| r.url.ends_with("/mfa/verify"))
+            .unwrap();
+        let body = String::from_utf8(verify.body.clone()).unwrap();
+        assert_eq!(body, "formkey=mfa%3A1%3Aabc&OtpCode=123456");
+        assert!(verify
+            .headers
+            .contains(&("Cookie".into(), "Wilma2MfaID=pending".into())));
+    }
+
+    #[tokio::test]
+    async fn signs_in_through_an_identity_provider() {
+        const IDP: &str = "https://idp.example.com";
+
+        let transport = wilma()
+            .with_response(
+                Method::Get,
+                format!("{}/saml/login/1", SERVER),
+                Response::new(302, "")
+                    .with_header("Location", format!("{}/authorize?request=r", IDP))
+                    .with_header("Set-Cookie", "WilmaSso=state; path=/"),
+            )
+            .with_response(
+                Method::Get,
+                format!("{}/authorize?request=r", IDP),
+                Response::new(
+                    200,
+                    r#"<form method="post" action="/login">
+                         <input type="hidden" name="AuthState" value="s1" />
+                         <input name="username" />
+                         <input type="password" name="password" />
+                       </form>"#,
+                )
+                .with_header("Set-Cookie", "IdpSession=i; path=/"),
+            )
+            .with_response(
+                Method::Post,
+                format!("{}/login", IDP),
+                Response::new(
+                    200,
+                    r#"<form method="post" action="https://wilma.example.com/saml/acs">
+                         <input type="hidden" name="SAMLResponse" value="assertion" />
+                       </form>"#,
+                ),
+            )
+            .with_response(
+                Method::Post,
+                format!("{}/saml/acs", SERVER),
+                Response::new(303, "")
+                    .with_header("Location", "/")
+                    .with_header("Set-Cookie", "Wilma2SID=sid; path=/"),
+            );
+
+        let login =
+            match Client::start_sso_with(transport, "wilma.example.com", "/saml/login/1", None)
+                .await
+                .unwrap()
+            {
+                Sso::Authorize(login) => login,
+                Sso::Complete(_) => panic!("the identity provider should ask for a password"),
+            };
+
+        match login.step() {
+            SsoStep::Form { url, form } => {
+                assert_eq!(url, &format!("{}/login", IDP));
+                assert_eq!(form.inputs, vec!["username", "password"]);
+            }
+            step => panic!("unexpected step {:?}", step),
+        }
+
+        let client = match login
+            .submit(&[("username", "user"), ("password", "hunter2")])
+            .await
+            .unwrap()
+        {
+            Sso::Complete(client) => client,
+            Sso::Authorize(_) => panic!("the login should be complete"),
+        };
+        assert_eq!(client.session().id.expose(), "sid");
+
+        let requests = client.transport().requests();
+        let idp_login = requests
+            .iter()
+            .find(|r| r.url == format!("{}/login", IDP))
+            .unwrap();
+        assert_eq!(
+            String::from_utf8(idp_login.body.clone()).unwrap(),
+            "AuthState=s1&username=user&password=hunter2"
+        );
+        assert!(idp_login
+            .headers
+            .contains(&("Cookie".into(), "IdpSession=i".into())));
+
+        let acs = requests
+            .iter()
+            .find(|r| r.url.ends_with("/saml/acs"))
+            .unwrap();
+        assert!(acs
+            .headers
+            .contains(&("Cookie".into(), "WilmaSso=state".into())));
+    }
+
+    #[tokio::test]
+    async fn completes_browser_sign_in_with_the_callback() {
+        let transport = wilma()
+            .with_response(
+                Method::Get,
+                format!("{}/oidc/login", SERVER),
+                Response::new(302, "")
+                    .with_header("Location", "https://idp.example.com/oauth?state=s")
+                    .with_header("Set-Cookie", "WilmaOidc=s; path=/"),
+            )
+            .with_response(
+                Method::Get,
+                "https://idp.example.com/oauth?state=s",
+                Response::new(200, "<script>signIn()</script>"),
+            )
+            .with_response(
+                Method::Get,
+                format!("{}/oidc/callback?code=c&state=s", SERVER),
+                Response::new(303, "").with_header("Set-Cookie", "Wilma2SID=sid; path=/"),
+            );
+
+        let login = match Client::start_sso_with(transport, "wilma.example.com", "oidc/login", None)
+            .await
+            .unwrap()
+        {
+            Sso::Authorize(login) => login,
+            Sso::Complete(_) => panic!("the user should sign in in a browser"),
+        };
+        assert_eq!(
+            login.step(),
+            &SsoStep::Browser {
+                url: "https://idp.example.com/oauth?state=s".into()
+            }
+        );
+
+        let callback = format!("{}/oidc/callback?code=c&state=s", SERVER);
+        match login.complete_with_callback(&callback).await.unwrap() {
+            Sso::Complete(client) => {
+                let requests = client.transport().requests();
+                let callback = requests.iter().find(|r| r.url == callback).unwrap();
+                assert!(callback
+                    .headers
+                    .contains(&("Cookie".into(), "WilmaOidc=s".into())));
+            }
+            Sso::Authorize(_) => panic!("the login should be complete"),
+        }
+    }
+
+    #[tokio::test]
+    async fn rejects_wrong_codes() {
+        let transport = wilma().with_response(
+            Method::Post,
+            format!("{}/mfa/verify", SERVER),
+            Response::new(303, "").with_header("Location", "/mfa"),
+        );
+
+        let error = Client::login_as(transport, credentials(), None)
+            .await
+            .err()
+            .unwrap();
+        assert!(matches!(
+            error.downcast_ref::<WilmaError>(),
+            Some(WilmaError::MfaRequired)
+        ));
+
+        // The first code is wrong, the second one is accepted.
+        let transport = wilma()
+            .with_response(
+                Method::Post,
+                format!("{}/mfa/verify", SERVER),
+                Response::new(303, "").with_header("Location", "/mfa"),
+            )
+            .with_response(
+                Method::Post,
+                format!("{}/mfa/verify", SERVER),
+                Response::new(303, "").with_header("Set-Cookie", "Wilma2SID=sid; path=/"),
+            );
+
+        let pending = match Client::start_login_with(transport, credentials(), None)
+            .await
+            .unwrap()
+        {
+            Login::MfaRequired(pending) => pending,
+            Login::Complete(_) => panic!("the login should require a code"),
+        };
+
+        let rejected = pending.complete("000000").await.err().unwrap();
+        assert!(matches!(
+            rejected.error.downcast_ref::<WilmaError>(),
+            Some(WilmaError::InvalidMfaCode)
+        ));
+
+        let client = rejected.pending.unwrap().complete("123456").await.unwrap();
+        assert_eq!(client.session().id.expose(), "sid");
+    }
+
+    #[test]
+    fn never_puts_the_code_in_a_checkbox() {
+        let page = Page::parse(
+            r#"<form method="post" action="/mfa/verify">
+                 <input type="checkbox" name="TrustDevice" />
+                 <input type="text" name="Verification" />
+               </form>"#,
+        );
+        let form = parse_forms(&page).remove(0);
+        assert_eq!(code_field(&form), Some("Verification"));
+
+        let page = Page::parse(
+            r#"<form method="post" action="/mfa/verify">
+                 <input type="text" name="Comment" />
+                 <input type="text" name="OtpCode" />
+               </form>"#,
+        );
+        let form = parse_forms(&page).remove(0);
+        assert_eq!(code_field(&form), Some("OtpCode"));
+    }
+}
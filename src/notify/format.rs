@@ -0,0 +1,203 @@
+use super::Notification;
+use crate::{
+    locale::{Language, Localize},
+    watcher::Event,
+    wilma::{Exam, Grade, Message, News, Note, Reservation},
+};
+
+/// Formats a value into a human-readable notification.
+pub trait ToNotification {
+    fn to_notification(&self) -> Notification;
+}
+
+impl ToNotification for Exam {
+    fn to_notification(&self) -> Notification {
+        let teachers: Vec<&str> = self.teachers.iter().map(|t| t.name.as_str()).collect();
+
+        let mut body = format!("{}\nDate: {}", self.course_title, self.date);
+
+        if let Some(name) = &self.name {
+            body.push_str(&format!("\nTopic: {}", name));
+        }
+
+        if !teachers.is_empty() {
+            body.push_str(&format!("\nTeachers: {}", teachers.join(", ")));
+        }
+
+        if let Some(grade) = &self.grade {
+            body.push_str(&format!("\nGrade: {}", grade));
+        }
+
+        Notification::new(format!("Exam: {}", self.course), body)
+    }
+}
+
+impl ToNotification for Reservation {
+    fn to_notification(&self) -> Notification {
+        let groups: Vec<&str> = self.groups.iter().map(|g| g.caption().as_str()).collect();
+        let rooms: Vec<&str> = self
+            .groups
+            .iter()
+            .flat_map(|g| g.rooms().iter().map(|r| r.long_caption().as_str()))
+            .collect();
+
+        let mut body = format!(
+            "{} {}-{}",
+            self.weekday.fmt(),
+            self.start.fmt(),
+            self.end.fmt()
+        );
+
+        if !rooms.is_empty() {
+            body.push_str(&format!("\nRoom: {}", rooms.join(", ")));
+        }
+
+        Notification::new(format!("Lesson: {}", groups.join(", ")), body)
+    }
+}
+
+impl ToNotification for Message {
+    fn to_notification(&self) -> Notification {
+        let sender = self.sender.as_deref().unwrap_or("Unknown sender");
+
+        Notification::new(
+            format!("New message: {}", self.subject),
+            format!("From: {}\nSent: {}", sender, self.timestamp),
+        )
+    }
+}
+
+impl ToNotification for News {
+    fn to_notification(&self) -> Notification {
+        let description = self.description.as_deref().unwrap_or_default();
+
+        Notification::new(
+            format!("News: {}", self.title),
+            format!("Published: {}\n{}", self.published, description),
+        )
+    }
+}
+
+impl ToNotification for Note {
+    fn to_notification(&self) -> Notification {
+        let mut body = format!("Date: {}", self.date);
+
+        if let Some(course) = &self.course {
+            body.push_str(&format!("\nCourse: {}", course));
+        }
+
+        if let Some(reason) = &self.reason {
+            body.push_str(&format!("\nReason: {}", reason));
+        }
+
+        Notification::new(
+            format!("New note: {}", self.note_type.localize(Language::English)),
+            body,
+        )
+    }
+}
+
+impl ToNotification for Grade {
+    fn to_notification(&self) -> Notification {
+        Notification::new(
+            format!("Grade: {}", self.course_name),
+            format!("{}: {}", self.course_name, self.grade),
+        )
+    }
+}
+
+impl ToNotification for Event {
+    fn to_notification(&self) -> Notification {
+        use Event::*;
+
+        match self {
+            NewMessage(message) => message.to_notification(),
+            NewsPublished(news) => news.to_notification(),
+            NoteAdded(note) => note.to_notification(),
+            GradeAdded(grade) => grade.to_notification(),
+            GradeChanged { grade, previous } => Notification::new(
+                format!("Grade changed: {}", grade.course_name),
+                format!("{}: {} (was {})", grade.course_name, grade.grade, previous),
+            ),
+            ExamScheduled(exam) => exam.to_notification(),
+            ExamGraded(exam) => {
+                let notification = exam.to_notification();
+                Notification::new(format!("Exam graded: {}", exam.course), notification.body)
+            }
+            LessonAdded(reservation) => reservation.to_notification(),
+            LessonCancelled {
+                lesson: Some(lesson),
+                ..
+            } => Notification::new(
+                format!("Lesson cancelled: {}", lesson.course),
+                format!(
+                    "{} {}-{} was removed from the schedule.",
+                    lesson.weekday.fmt(),
+                    lesson.start.fmt(),
+                    lesson.end.fmt()
+                ),
+            ),
+            LessonCancelled {
+                reservation_id,
+                lesson: None,
+            } => Notification::new(
+                "Lesson cancelled",
+                format!(
+                    "Reservation {} was removed from the schedule.",
+                    reservation_id
+                ),
+            ),
+            RoomChanged { reservation, .. } => {
+                let notification = reservation.to_notification();
+                Notification::new(
+                    format!("Room changed: {}", notification.title),
+                    notification.body,
+                )
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{watcher::Lesson, wilma::schedule::Weekday};
+
+    #[test]
+    fn formats_notes() {
+        let note: Note = serde_json::from_str(
+            r#"{ "Id": 1, "Date": "2022-03-14", "Type": "late", "Course": "MAA1" }"#,
+        )
+        .unwrap();
+        let notification = note.to_notification();
+
+        assert_eq!(notification.title, "New note: Late");
+        assert_eq!(notification.body, "Date: 2022-03-14\nCourse: MAA1");
+    }
+
+    #[test]
+    fn formats_cancelled_lessons() {
+        let event = Event::LessonCancelled {
+            reservation_id: 7,
+            lesson: Some(Lesson {
+                course: "MAA1".into(),
+                weekday: Weekday::Monday,
+                start: "8:15".parse().unwrap(),
+                end: "9:30".parse().unwrap(),
+            }),
+        };
+        let notification = event.to_notification();
+
+        assert_eq!(notification.title, "Lesson cancelled: MAA1");
+        assert_eq!(
+            notification.body,
+            "Monday 8:15-9:30 was removed from the schedule."
+        );
+
+        let event = Event::LessonCancelled {
+            reservation_id: 7,
+            lesson: None,
+        };
+        assert!(event.to_notification().body.contains("Reservation 7"));
+    }
+}
@@ -0,0 +1,75 @@
+use super::{Notification, Notifier};
+use async_trait::async_trait;
+use std::io::{self, Write};
+use std::sync::Mutex;
+
+/// Writes every notification as a single line of JSON.
+pub struct JsonLinesNotifier<W: Write + Send> {
+    writer: Mutex<W>,
+}
+
+impl<W: Write + Send> JsonLinesNotifier<W> {
+    pub fn new(writer: W) -> Self {
+        Self {
+            writer: Mutex::new(writer),
+        }
+    }
+
+    /// Returns the underlying writer.
+    pub fn into_inner(self) -> W {
+        self.writer.into_inner().unwrap_or_else(|e| e.into_inner())
+    }
+}
+
+impl JsonLinesNotifier<io::Stdout> {
+    /// A notifier writing to the standard output.
+    pub fn stdout() -> Self {
+        Self::new(io::stdout())
+    }
+}
+
+#[async_trait]
+impl<W: Write + Send> Notifier for JsonLinesNotifier<W> {
+    async fn notify(&self, notification: &Notification) -> anyhow::Result<()> {
+        let line = serde_json::to_string(notification)?;
+
+        let mut writer = self.writer.lock().unwrap_or_else(|e| e.into_inner());
+        writeln!(writer, "{}", line)?;
+        writer.flush()?;
+
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn writes_a_line_per_notification() {
+        let notifier = JsonLinesNotifier::new(Vec::new());
+
+        notifier
+            .notify(&Notification::new("Hei", "Rivi 1\nRivi 2"))
+            .await
+            .unwrap();
+        notifier
+            .notify(&Notification::new("Moi", ""))
+            .await
+            .unwrap();
+
+        let output = String::from_utf8(notifier.into_inner()).unwrap();
+        let lines: Vec<Notification> = output
+            .lines()
+            .map(|l| serde_json::from_str(l).unwrap())
+            .collect();
+
+        assert_eq!(
+            lines,
+            [
+                Notification::new("Hei", "Rivi 1\nRivi 2"),
+                Notification::new("Moi", "")
+            ]
+        );
+    }
+}
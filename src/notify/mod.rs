@@ -0,0 +1,41 @@
+use async_trait::async_trait;
+use serde::{Deserialize, Serialize};
+
+pub mod format;
+pub use format::ToNotification;
+
+pub mod json_lines;
+pub use json_lines::JsonLinesNotifier;
+
+#[cfg(feature = "smtp")]
+pub mod smtp;
+#[cfg(feature = "smtp")]
+pub use smtp::SmtpNotifier;
+
+pub mod webhook;
+pub use webhook::WebhookNotifier;
+
+/// A human-readable notification.
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, Eq)]
+pub struct Notification {
+    /// A one-line summary, used as the email subject for example.
+    pub title: String,
+
+    /// The full text of the notification.
+    pub body: String,
+}
+
+impl Notification {
+    pub fn new<T: Into<String>, U: Into<String>>(title: T, body: U) -> Self {
+        Self {
+            title: title.into(),
+            body: body.into(),
+        }
+    }
+}
+
+/// Something notifications can be pushed to.
+#[async_trait]
+pub trait Notifier: Send + Sync {
+    async fn notify(&self, notification: &Notification) -> anyhow::Result<()>;
+}
@@ -0,0 +1,133 @@
+use super::{Notification, Notifier};
+use async_trait::async_trait;
+use lettre::{
+    message::Mailbox, transport::smtp::authentication::Credentials, AsyncSmtpTransport,
+    AsyncTransport, Message, Tokio1Executor,
+};
+
+/// Sends notifications as plain text emails.
+pub struct SmtpNotifier {
+    transport: AsyncSmtpTransport<Tokio1Executor>,
+    from: Mailbox,
+    to: Mailbox,
+}
+
+impl SmtpNotifier {
+    /// Creates a notifier from an already configured transport.
+    pub fn new(
+        transport: AsyncSmtpTransport<Tokio1Executor>,
+        from: &str,
+        to: &str,
+    ) -> anyhow::Result<Self> {
+        Ok(Self {
+            transport,
+            from: from.parse()?,
+            to: to.parse()?,
+        })
+    }
+
+    /// Connects to an SMTP relay using TLS and authenticates with the given username and
+    /// password.
+    pub fn relay(
+        host: &str,
+        username: &str,
+        password: &str,
+        from: &str,
+        to: &str,
+    ) -> anyhow::Result<Self> {
+        let transport = AsyncSmtpTransport::<Tokio1Executor>::relay(host)?
+            .credentials(Credentials::new(username.into(), password.into()))
+            .build();
+
+        Self::new(transport, from, to)
+    }
+
+    /// Connects to an SMTP server without encryption or authentication. Only meant for local
+    /// mail servers.
+    pub fn unencrypted(host: &str, port: u16, from: &str, to: &str) -> anyhow::Result<Self> {
+        let transport = AsyncSmtpTransport::<Tokio1Executor>::builder_dangerous(host)
+            .port(port)
+            .build();
+
+        Self::new(transport, from, to)
+    }
+}
+
+#[async_trait]
+impl Notifier for SmtpNotifier {
+    async fn notify(&self, notification: &Notification) -> anyhow::Result<()> {
+        let email = Message::builder()
+            .from(self.from.clone())
+            .to(self.to.clone())
+            .subject(&notification.title)
+            .body(notification.body.clone())?;
+
+        self.transport.send(email).await?;
+
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tokio::io::{AsyncBufReadExt, AsyncWriteExt, BufReader};
+    use tokio::net::TcpListener;
+
+    /// A minimal SMTP server that accepts a single email and returns its contents.
+    async fn serve_one(listener: TcpListener) -> String {
+        let (socket, _) = listener.accept().await.unwrap();
+        let (reader, mut writer) = socket.into_split();
+        let mut lines = BufReader::new(reader).lines();
+
+        writer.write_all(b"220 localhost\r\n").await.unwrap();
+
+        let mut data = String::new();
+        let mut in_data = false;
+
+        while let Some(line) = lines.next_line().await.unwrap() {
+            if in_data {
+                if line == "." {
+                    in_data = false;
+                    writer.write_all(b"250 OK\r\n").await.unwrap();
+                } else {
+                    data.push_str(&line);
+                    data.push('\n');
+                }
+            } else if line.starts_with("DATA") {
+                in_data = true;
+                writer.write_all(b"354 Go ahead\r\n").await.unwrap();
+            } else if line.starts_with("QUIT") {
+                writer.write_all(b"221 Bye\r\n").await.unwrap();
+                break;
+            } else {
+                writer.write_all(b"250 OK\r\n").await.unwrap();
+            }
+        }
+
+        data
+    }
+
+    #[tokio::test]
+    async fn sends_email() {
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let port = listener.local_addr().unwrap().port();
+        let server = tokio::spawn(serve_one(listener));
+
+        let notifier =
+            SmtpNotifier::unencrypted("127.0.0.1", port, "wilma@example.com", "me@example.com")
+                .unwrap();
+
+        notifier
+            .notify(&Notification::new("New message: Hello", "From: Teacher"))
+            .await
+            .unwrap();
+
+        // Without connection pooling the transport sends QUIT after every email.
+        let email = server.await.unwrap();
+
+        assert!(email.contains("Subject: New message: Hello"));
+        assert!(email.contains("To: me@example.com"));
+        assert!(email.contains("From: Teacher"));
+    }
+}
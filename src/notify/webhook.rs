@@ -0,0 +1,126 @@
+use super::{Notification, Notifier};
+#[cfg(feature = "reqwest")]
+use crate::transport::ReqwestTransport;
+use crate::transport::{DefaultTransport, Request, Transport};
+use async_trait::async_trait;
+use hmac::{Hmac, Mac};
+use sha2::Sha256;
+
+/// The header containing the HMAC-SHA256 signature of the request body.
+pub const SIGNATURE_HEADER: &str = "X-Signature-256";
+
+/// Posts notifications as JSON to an HTTP endpoint.
+///
+/// If a secret is set, the body is signed with HMAC-SHA256 and the signature is sent in the
+/// `X-Signature-256` header as `sha256=<hex digest>`, so the receiver can verify the sender.
+pub struct WebhookNotifier<T: Transport = DefaultTransport> {
+    transport: T,
+    url: String,
+    secret: Option<Vec<u8>>,
+}
+
+#[cfg(feature = "reqwest")]
+impl WebhookNotifier {
+    pub fn new<U: Into<String>>(url: U) -> Self {
+        Self::with_transport(ReqwestTransport::from_client(reqwest::Client::new()), url)
+    }
+}
+
+impl<T: Transport> WebhookNotifier<T> {
+    /// Sends the requests using the given transport.
+    pub fn with_transport<U: Into<String>>(transport: T, url: U) -> Self {
+        Self {
+            transport,
+            url: url.into(),
+            secret: None,
+        }
+    }
+
+    /// Signs every request with the given secret.
+    pub fn with_secret<S: AsRef<[u8]>>(mut self, secret: S) -> Self {
+        self.secret = Some(secret.as_ref().to_vec());
+        self
+    }
+}
+
+/// Computes the value of the signature header for the given body.
+pub fn sign(secret: &[u8], body: &[u8]) -> String {
+    let mut mac = Hmac::<Sha256>::new_from_slice(secret).expect("HMAC accepts keys of any size");
+    mac.update(body);
+
+    format!("sha256={}", hex::encode(mac.finalize().into_bytes()))
+}
+
+#[async_trait]
+impl<T: Transport> Notifier for WebhookNotifier<T> {
+    async fn notify(&self, notification: &Notification) -> anyhow::Result<()> {
+        let body = serde_json::to_vec(notification)?;
+
+        let mut request =
+            Request::post(&self.url, body.clone()).header("Content-Type", "application/json");
+
+        if let Some(secret) = &self.secret {
+            request = request.header(SIGNATURE_HEADER, sign(secret, &body));
+        }
+
+        let response = self.transport.send(request).await?;
+
+        if !response.is_success() {
+            anyhow::bail!("The webhook responded with status {}.", response.status);
+        }
+
+        Ok(())
+    }
+}
+
+#[cfg(all(test, feature = "reqwest"))]
+mod tests {
+    use super::*;
+    use tokio::io::{AsyncReadExt, AsyncWriteExt};
+    use tokio::net::TcpListener;
+
+    #[tokio::test]
+    async fn posts_signed_json() {
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let url = format!("http://{}/hook", listener.local_addr().unwrap());
+
+        let server = tokio::spawn(async move {
+            let (mut socket, _) = listener.accept().await.unwrap();
+            let mut request = Vec::new();
+            let mut buffer = [0; 4096];
+
+            // Read until the whole body (the end of the JSON object) has arrived.
+            while !request.ends_with(b"}") {
+                let n = socket.read(&mut buffer).await.unwrap();
+                assert!(n > 0, "the connection closed before the whole body arrived");
+                request.extend_from_slice(&buffer[..n]);
+            }
+
+            socket
+                .write_all(b"HTTP/1.1 200 OK\r\nContent-Length: 0\r\n\r\n")
+                .await
+                .unwrap();
+
+            String::from_utf8(request).unwrap()
+        });
+
+        let notification = Notification::new("Title", "Body");
+        WebhookNotifier::new(url)
+            .with_secret("secret")
+            .notify(&notification)
+            .await
+            .unwrap();
+
+        let request = server.await.unwrap();
+        let (headers, body) = request.split_once("\r\n\r\n").unwrap();
+
+        assert!(headers.starts_with("POST /hook"));
+        assert_eq!(
+            serde_json::from_str::<Notification>(body).unwrap(),
+            notification
+        );
+
+        let signature = format!("{}: {}", SIGNATURE_HEADER, sign(b"secret", body.as_bytes()));
+        assert!(headers.to_lowercase().contains(&signature.to_lowercase()));
+    }
+}
@@ -0,0 +1,101 @@
+use super::{missing, text, Page};
+
+/// The identity of the first role on the role selection page, with a trailing slash, for
+/// example `!0123456/`. It's appended to the server URL to get the base URL.
+#[tracing::instrument(level = "trace", skip_all)]
+pub fn parse_identity(page: &Page) -> anyhow::Result<String> {
+    let href = page.extract("role").ok_or_else(|| missing("role link"))?;
+
+    Ok(href.trim_start_matches('/').to_string())
+}
+
+/// Parses every role an account can choose from on the role selection page, as
+/// `(identity, name)` pairs. The identity is the path segment appended to the base URL, for
+/// example `!0123456`.
+#[tracing::instrument(level = "trace", skip_all)]
+pub fn parse_roles(page: &Page) -> Vec<(String, String)> {
+    let attribute = page.rule_attribute("role").unwrap_or("href");
+
+    page.select_rule("role")
+        .into_iter()
+        .filter_map(|link| {
+            let identity = link.value().attr(attribute)?.trim_matches('/').to_string();
+            Some((identity, text(&link)))
+        })
+        .collect()
+}
+
+/// An HTML form, for the login steps that need one filled in.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Form {
+    /// The `action` attribute as written in the page, possibly relative.
+    pub action: String,
+
+    /// `get` or `post`, in lowercase.
+    pub method: String,
+
+    /// Hidden inputs and their values, to be sent back as is.
+    pub hidden: Vec<(String, String)>,
+
+    /// The names of the inputs to fill in, in document order.
+    pub inputs: Vec<String>,
+
+    /// The names of the inputs that are checkboxes or radio buttons. They're also in `inputs`.
+    pub choices: Vec<String>,
+}
+
+impl Form {
+    /// The inputs that take text, like a username or a verification code.
+    pub fn text_inputs(&self) -> Vec<&str> {
+        self.inputs
+            .iter()
+            .filter(|name| !self.choices.contains(name))
+            .map(String::as_str)
+            .collect()
+    }
+}
+
+/// Parses every form in the page.
+#[tracing::instrument(level = "trace", skip_all)]
+pub fn parse_forms(page: &Page) -> Vec<Form> {
+    let inputs = match scraper::Selector::parse("input[name]") {
+        Ok(selector) => selector,
+        Err(_) => return Vec::new(),
+    };
+
+    page.select_rule("form")
+        .into_iter()
+        .map(|form| {
+            let mut hidden = Vec::new();
+            let mut names = Vec::new();
+            let mut choices = Vec::new();
+
+            for input in form.select(&inputs) {
+                let name = input.value().attr("name").unwrap_or_default().to_string();
+                let value = input.value().attr("value").unwrap_or_default().to_string();
+
+                match input.value().attr("type").unwrap_or("text") {
+                    "hidden" => hidden.push((name, value)),
+                    "submit" | "button" | "reset" | "image" => {}
+                    "checkbox" | "radio" => {
+                        choices.push(name.clone());
+                        names.push(name);
+                    }
+                    _ => names.push(name),
+                }
+            }
+
+            Form {
+                action: form.value().attr("action").unwrap_or_default().into(),
+                method: form
+                    .value()
+                    .attr("method")
+                    .unwrap_or("get")
+                    .to_ascii_lowercase(),
+                hidden,
+                inputs: names,
+                choices,
+            }
+        })
+        .collect()
+}
@@ -0,0 +1,56 @@
+use super::{text, Page};
+
+/// Whether the page has the username and password form.
+#[tracing::instrument(level = "trace", skip_all)]
+pub fn parse_password_login(page: &Page) -> bool {
+    !page.select_rule("password_login").is_empty()
+}
+
+/// Parses the links to external identity providers, as `(name, url)` pairs. The links are
+/// matched with the `sso_provider` rule, and the URL is the attribute of the rule.
+#[tracing::instrument(level = "trace", skip_all)]
+pub fn parse_sso_providers(page: &Page) -> Vec<(String, String)> {
+    let attribute = page.rule_attribute("sso_provider").unwrap_or("href");
+
+    page.select_rule("sso_provider")
+        .into_iter()
+        .filter_map(|link| Some((text(&link), link.value().attr(attribute)?.to_string())))
+        .collect()
+}
+
+/// Whether the page mentions two-step verification.
+#[tracing::instrument(level = "trace", skip_all)]
+pub fn parse_mfa_hint(page: &Page) -> bool {
+    !page.select_rule("mfa_hint").is_empty()
+}
+
+/// Parses the maintenance notice, if the page has one.
+#[tracing::instrument(level = "trace", skip_all)]
+pub fn parse_maintenance(page: &Page) -> Option<String> {
+    page.select_rule("maintenance").first().map(text)
+}
+
+/// Parses the languages the user interface is available in, as language codes like `fi`.
+#[tracing::instrument(level = "trace", skip_all)]
+pub fn parse_languages(page: &Page) -> Vec<String> {
+    let mut languages: Vec<String> = Vec::new();
+
+    for element in page.select_rule("language") {
+        let language = element
+            .value()
+            .attr("hreflang")
+            .or_else(|| element.value().attr("lang"))
+            .unwrap_or_default();
+        let language = language
+            .split('-')
+            .next()
+            .unwrap_or_default()
+            .to_lowercase();
+
+        if !language.is_empty() && !languages.contains(&language) {
+            languages.push(language);
+        }
+    }
+
+    languages
+}
@@ -0,0 +1,230 @@
+//! Extracting data from Wilma's HTML pages.
+//!
+//! A page is parsed once into a `Page`, and every parser queries it with the CSS selectors of a
+//! named rule (see `selectors`). Parsers never panic: anything missing from the page is reported
+//! as `Error::UnexpectedPage`.
+
+use crate::Error as WilmaError;
+use scraper::{ElementRef, Html, Selector};
+use std::sync::Arc;
+
+pub mod core;
+pub mod login_page;
+pub mod selectors;
+pub use selectors::{Rule, SelectorOverrides, Selectors};
+pub mod user;
+
+/// An HTML page, parsed once and queried with CSS selectors.
+pub struct Page {
+    html: Html,
+    selectors: Arc<Selectors>,
+}
+
+impl Page {
+    /// Parses a page that is queried with the built-in selectors.
+    pub fn parse(document: &str) -> Self {
+        Self::with_selectors(document, Selectors::builtin())
+    }
+
+    pub fn with_selectors(document: &str, selectors: Arc<Selectors>) -> Self {
+        Self {
+            html: Html::parse_document(document),
+            selectors,
+        }
+    }
+
+    /// Every element matching a rule, in document order.
+    pub fn select_rule(&self, rule: &str) -> Vec<ElementRef<'_>> {
+        match self.selectors.rule(rule) {
+            Some(rule) => self.select(&rule.selector),
+            None => Vec::new(),
+        }
+    }
+
+    /// What a rule extracts from the elements it matches: the attribute of the rule, or the
+    /// trimmed text. Elements without the attribute or any text are skipped.
+    pub fn extract_all(&self, rule: &str) -> Vec<String> {
+        let attribute = self
+            .selectors
+            .rule(rule)
+            .and_then(|r| r.attribute.as_deref());
+
+        self.select_rule(rule)
+            .into_iter()
+            .filter_map(|element| match attribute {
+                Some(attribute) => element.value().attr(attribute).map(String::from),
+                None => Some(text(&element)).filter(|text| !text.is_empty()),
+            })
+            .collect()
+    }
+
+    /// What a rule extracts from the first element it matches. See `Page::extract_all`.
+    pub fn extract(&self, rule: &str) -> Option<String> {
+        self.extract_all(rule).into_iter().next()
+    }
+
+    /// The attribute of a rule, if it has one.
+    pub fn rule_attribute(&self, rule: &str) -> Option<&str> {
+        self.selectors
+            .rule(rule)
+            .and_then(|r| r.attribute.as_deref())
+    }
+
+    /// The parsed document.
+    pub fn html(&self) -> &Html {
+        &self.html
+    }
+
+    /// Every element matching the selector, in document order. An invalid selector matches
+    /// nothing.
+    pub fn select(&self, selector: &str) -> Vec<ElementRef<'_>> {
+        match parse_selector(selector) {
+            Some(selector) => self.html.select(&selector).collect(),
+            None => Vec::new(),
+        }
+    }
+}
+
+/// The text of an element and its children, with whitespace collapsed.
+pub fn text(element: &ElementRef) -> String {
+    element
+        .text()
+        .flat_map(|text| text.split_whitespace())
+        .collect::<Vec<_>>()
+        .join(" ")
+}
+
+fn parse_selector(selector: &str) -> Option<Selector> {
+    Selector::parse(selector)
+        .inspect_err(|_| tracing::warn!(selector, "invalid selector"))
+        .ok()
+}
+
+/// The error for something that should be on a page but isn't.
+pub(crate) fn missing(what: &str) -> anyhow::Error {
+    anyhow::Error::from(WilmaError::UnexpectedPage)
+        .context(format!("Couldn't find the {} on the page.", what))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use proptest::prelude::*;
+
+    pub const FIXTURES: &[&str] = &[
+        include_str!("fixtures/student_front_page.html"),
+        include_str!("fixtures/guardian_front_page.html"),
+        include_str!("fixtures/role_selection.html"),
+        include_str!("fixtures/login_page.html"),
+    ];
+
+    /// Runs every parser on a document. Only panics matter here, not the results.
+    fn parse_everything(document: &str) {
+        let page = Page::parse(document);
+
+        let _ = user::parse_name(&page);
+        let _ = user::parse_school(&page);
+        let _ = user::parse_formkey(&page);
+        let _ = core::parse_identity(&page);
+        let _ = core::parse_roles(&page);
+        let _ = core::parse_forms(&page);
+        let _ = login_page::parse_password_login(&page);
+        let _ = login_page::parse_sso_providers(&page);
+        let _ = login_page::parse_mfa_hint(&page);
+        let _ = login_page::parse_maintenance(&page);
+        let _ = login_page::parse_languages(&page);
+    }
+
+    #[test]
+    fn parses_the_front_page() {
+        let page = Page::parse(FIXTURES[0]);
+
+        assert_eq!(user::parse_name(&page).unwrap(), "Meikäläinen Matti");
+        assert_eq!(user::parse_school(&page).unwrap(), "Esimerkkikoulu");
+        assert_eq!(
+            user::parse_formkey(&page).unwrap(),
+            "student:123456:0123456789abcdef"
+        );
+    }
+
+    #[test]
+    fn copes_with_reflowed_markup() {
+        // The guardian page spreads the elements over several lines, and has another element
+        // mentioning `teacher` before the profile.
+        let page = Page::parse(FIXTURES[1]);
+
+        assert_eq!(user::parse_name(&page).unwrap(), "Virtanen Maija");
+        assert_eq!(user::parse_school(&page).unwrap(), "Esimerkkilukio");
+        assert_eq!(
+            user::parse_formkey(&page).unwrap(),
+            "guardian:654321:fedcba9876543210"
+        );
+    }
+
+    #[test]
+    fn parses_roles() {
+        let page = Page::parse(FIXTURES[2]);
+
+        assert_eq!(core::parse_identity(&page).unwrap(), "!0123456/");
+        assert_eq!(
+            core::parse_roles(&page),
+            vec![
+                ("!0123456".into(), "Meikäläinen Matti".into()),
+                ("!0654321".into(), "Meikäläinen Maija".into()),
+            ]
+        );
+    }
+
+    #[test]
+    fn uses_overridden_selectors() {
+        let selectors = Selectors::default()
+            .with_rule("name", Rule::new("#profile-name", None))
+            .unwrap();
+        let page = Page::with_selectors(
+            r#"<span class="teacher">Old</span><b id="profile-name">New</b>"#,
+            Arc::new(selectors),
+        );
+
+        assert_eq!(user::parse_name(&page).unwrap(), "New");
+    }
+
+    #[test]
+    fn reports_missing_elements() {
+        let page = Page::parse("<html><body><p>Huoltokatko</p></body></html>");
+
+        let error = user::parse_name(&page).unwrap_err();
+        assert!(matches!(
+            error.downcast_ref::<WilmaError>(),
+            Some(WilmaError::UnexpectedPage)
+        ));
+        assert!(core::parse_identity(&page).is_err());
+        assert!(
+            user::parse_formkey(&Page::parse(r#"<input name="formkey" value="x" />"#)).is_err()
+        );
+    }
+
+    proptest! {
+        #[test]
+        fn parsers_never_panic_on_arbitrary_text(document in ".*") {
+            parse_everything(&document);
+        }
+
+        #[test]
+        fn parsers_never_panic_on_arbitrary_markup(
+            document in r#"(<(span|a|input|form|div)( (class|name|href|value|type)="(teacher|school|formkey|text-style-link|/|hidden|[a-z!:/]{0,8})")*>|</(span|a|form|div)>|[^<>]{0,12}){0,40}"#
+        ) {
+            parse_everything(&document);
+        }
+
+        #[test]
+        fn parsers_never_panic_on_truncated_fixtures(
+            fixture in 0..FIXTURES.len(),
+            cut in any::<prop::sample::Index>(),
+        ) {
+            let fixture = FIXTURES[fixture];
+            let boundaries: Vec<usize> = fixture.char_indices().map(|(i, _)| i).collect();
+
+            parse_everything(&fixture[..boundaries[cut.index(boundaries.len())]]);
+        }
+    }
+}
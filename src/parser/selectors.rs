@@ -0,0 +1,278 @@
+//! The CSS selectors the parsers use, as data.
+//!
+//! Every parser looks up its rule by name from a `Selectors` table. The built-in table matches
+//! the current Wilma markup, and a `SelectorOverrides` file can replace rules for every API
+//! version or for specific ones, so a selector can be fixed without a new release:
+//!
+//! ```toml
+//! [default]
+//! name = { selector = ".profile .name" }
+//!
+//! [versions.13]
+//! formkey = { selector = "input#formkey", attribute = "value" }
+//! ```
+
+use scraper::Selector;
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::path::Path;
+use std::sync::{Arc, OnceLock};
+
+/// A CSS selector, and what to extract from the elements it matches.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Rule {
+    pub selector: String,
+
+    /// The attribute to extract. The text of the element is extracted if there's none.
+    #[serde(default)]
+    pub attribute: Option<String>,
+}
+
+impl Rule {
+    pub fn new<T: Into<String>>(selector: T, attribute: Option<&str>) -> Self {
+        Self {
+            selector: selector.into(),
+            attribute: attribute.map(String::from),
+        }
+    }
+}
+
+/// The names of the rules and their built-in selectors and attributes.
+pub const DEFAULTS: &[(&str, &str, Option<&str>)] = &[
+    // The user's name and school on the front page.
+    ("name", ".teacher", None),
+    ("school", ".school", None),
+    ("formkey", r#"input[name="formkey"]"#, Some("value")),
+    // Links to the roles of an account on the role selection page.
+    ("role", "a.text-style-link", Some("href")),
+    ("form", "form", None),
+    // The login page.
+    ("password_login", r#"form input[name="Password"]"#, None),
+    (
+        "sso_provider",
+        r#"a[href*="/saml/"], a[href*="/oidc/"], a[href*="/sso/"]"#,
+        Some("href"),
+    ),
+    (
+        "mfa_hint",
+        r#"[href*="mfa"], [action*="mfa"], .mfa, [data-mfa]"#,
+        None,
+    ),
+    (
+        "maintenance",
+        r#"[class*="maintenance"], #maintenance"#,
+        None,
+    ),
+    ("language", "html[lang], a[hreflang]", None),
+];
+
+/// Rules that differ in older API versions, and the last version that uses them, ordered from
+/// the newest version to the oldest. Like `compat::DIFFERENCES`, an entry has to be backed by a
+/// page captured from a server running that version; none are confirmed yet.
+pub const VERSIONS: &[(u32, &str, &str, Option<&str>)] = &[];
+
+/// A table of extraction rules by name. See `DEFAULTS` for the names.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Selectors {
+    rules: BTreeMap<String, Rule>,
+}
+
+impl Default for Selectors {
+    fn default() -> Self {
+        let rules = DEFAULTS
+            .iter()
+            .map(|(name, selector, attribute)| (name.to_string(), Rule::new(*selector, *attribute)))
+            .collect();
+
+        Self { rules }
+    }
+}
+
+impl Selectors {
+    /// The built-in table, shared by every page that isn't given another one.
+    pub fn builtin() -> Arc<Self> {
+        static BUILTIN: OnceLock<Arc<Selectors>> = OnceLock::new();
+        BUILTIN.get_or_init(|| Arc::new(Self::default())).clone()
+    }
+
+    /// The built-in table for the markup of an API version.
+    pub fn for_version(version: u32) -> Self {
+        let mut selectors = Self::default();
+
+        for (until, name, selector, attribute) in VERSIONS {
+            if version <= *until {
+                selectors
+                    .rules
+                    .insert(name.to_string(), Rule::new(*selector, *attribute));
+            }
+        }
+
+        selectors
+    }
+
+    pub fn rule(&self, name: &str) -> Option<&Rule> {
+        self.rules.get(name)
+    }
+
+    /// Replaces a rule. Fails if there's no rule with the name or the selector is invalid.
+    pub fn with_rule(mut self, name: &str, rule: Rule) -> anyhow::Result<Self> {
+        validate(name, &rule)?;
+        self.rules.insert(name.into(), rule);
+
+        Ok(self)
+    }
+}
+
+/// Rules that replace the built-in ones, for every API version (`default`) or for specific
+/// versions. Version-specific rules take precedence.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct SelectorOverrides {
+    #[serde(default)]
+    pub default: BTreeMap<String, Rule>,
+
+    /// Rules by API version. The keys are strings because TOML and JSON keys always are.
+    #[serde(default)]
+    pub versions: BTreeMap<String, BTreeMap<String, Rule>>,
+}
+
+impl SelectorOverrides {
+    pub fn from_json(data: &str) -> anyhow::Result<Self> {
+        let overrides: Self = serde_json::from_str(data)?;
+        overrides.validate()?;
+
+        Ok(overrides)
+    }
+
+    #[cfg(feature = "toml")]
+    pub fn from_toml(data: &str) -> anyhow::Result<Self> {
+        let overrides: Self = toml::from_str(data)?;
+        overrides.validate()?;
+
+        Ok(overrides)
+    }
+
+    /// Loads overrides from a `.json` file, or a `.toml` file with the `toml` feature.
+    pub fn load<P: AsRef<Path>>(path: P) -> anyhow::Result<Self> {
+        let path = path.as_ref();
+        let data = std::fs::read_to_string(path)?;
+
+        match path.extension().and_then(|e| e.to_str()) {
+            Some("json") => Self::from_json(&data),
+            #[cfg(feature = "toml")]
+            Some("toml") => Self::from_toml(&data),
+            _ => anyhow::bail!("Unsupported selector file {}.", path.display()),
+        }
+    }
+
+    /// The built-in table of an API version with the overrides for it applied. Without a
+    /// version, only the `default` overrides are applied to the latest table.
+    pub fn resolve(&self, api_version: Option<u32>) -> Selectors {
+        let mut selectors = api_version.map(Selectors::for_version).unwrap_or_default();
+        selectors.rules.extend(self.default.clone());
+
+        let version = self
+            .versions
+            .iter()
+            .find(|(version, _)| version.trim().parse().ok() == api_version);
+
+        if let Some((_, rules)) = version {
+            selectors.rules.extend(rules.clone());
+        }
+
+        selectors
+    }
+
+    fn validate(&self) -> anyhow::Result<()> {
+        for (name, rule) in &self.default {
+            validate(name, rule)?;
+        }
+
+        for (version, rules) in &self.versions {
+            if version.trim().parse::<u32>().is_err() {
+                anyhow::bail!(
+                    "Invalid API version {:?} in the selector overrides.",
+                    version
+                );
+            }
+
+            for (name, rule) in rules {
+                validate(name, rule)?;
+            }
+        }
+
+        Ok(())
+    }
+}
+
+fn validate(name: &str, rule: &Rule) -> anyhow::Result<()> {
+    if !DEFAULTS.iter().any(|(n, _, _)| *n == name) {
+        anyhow::bail!("Unknown selector rule {:?}.", name);
+    }
+
+    if Selector::parse(&rule.selector).is_err() {
+        anyhow::bail!("Invalid selector {:?} for {:?}.", rule.selector, name);
+    }
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const OVERRIDES: &str = r#"{
+        "default": { "name": { "selector": ".profile .name" } },
+        "versions": {
+            "13": { "formkey": { "selector": "input#formkey", "attribute": "value" } }
+        }
+    }"#;
+
+    #[test]
+    fn layers_overrides_by_version() {
+        let overrides = SelectorOverrides::from_json(OVERRIDES).unwrap();
+
+        let selectors = overrides.resolve(Some(13));
+        assert_eq!(selectors.rule("name").unwrap().selector, ".profile .name");
+        assert_eq!(selectors.rule("formkey").unwrap().selector, "input#formkey");
+        assert_eq!(
+            selectors.rule("school"),
+            Selectors::default().rule("school")
+        );
+
+        let selectors = overrides.resolve(Some(12));
+        assert_eq!(selectors.rule("name").unwrap().selector, ".profile .name");
+        assert_eq!(
+            selectors.rule("formkey"),
+            Selectors::default().rule("formkey")
+        );
+    }
+
+    #[test]
+    fn rejects_invalid_overrides() {
+        let unknown = r#"{ "default": { "nickname": { "selector": "span" } } }"#;
+        assert!(SelectorOverrides::from_json(unknown).is_err());
+
+        let invalid = r#"{ "default": { "name": { "selector": "span[" } } }"#;
+        assert!(SelectorOverrides::from_json(invalid).is_err());
+
+        let version = r#"{ "versions": { "latest": {} } }"#;
+        assert!(SelectorOverrides::from_json(version).is_err());
+    }
+
+    #[cfg(feature = "toml")]
+    #[test]
+    fn reads_toml() {
+        let overrides = SelectorOverrides::from_toml(
+            r#"
+            [default]
+            name = { selector = ".profile .name" }
+
+            [versions.13]
+            formkey = { selector = "input#formkey", attribute = "value" }
+            "#,
+        )
+        .unwrap();
+
+        assert_eq!(overrides, SelectorOverrides::from_json(OVERRIDES).unwrap());
+    }
+}
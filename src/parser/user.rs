@@ -0,0 +1,25 @@
+use super::{missing, Page};
+
+/// The name of the logged in user.
+#[tracing::instrument(level = "trace", skip_all)]
+pub fn parse_name(page: &Page) -> anyhow::Result<String> {
+    page.extract("name").ok_or_else(|| missing("user's name"))
+}
+
+/// A formkey is like a CSRF token, see `User::formkey`. It's checked to have the structure
+/// `user_type:user_id:key`.
+#[tracing::instrument(level = "trace", skip_all)]
+pub fn parse_formkey(page: &Page) -> anyhow::Result<String> {
+    let formkey = page.extract("formkey").ok_or_else(|| missing("formkey"))?;
+
+    match formkey.splitn(3, ':').count() {
+        3 => Ok(formkey),
+        _ => Err(missing("formkey in the expected format")),
+    }
+}
+
+/// The name of the user's school.
+#[tracing::instrument(level = "trace", skip_all)]
+pub fn parse_school(page: &Page) -> anyhow::Result<String> {
+    page.extract("school").ok_or_else(|| missing("school"))
+}
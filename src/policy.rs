@@ -0,0 +1,387 @@
+use crate::runtime::Timer;
+use crate::transport::{Method, Request, Response, Transport, TransportError};
+use async_lock::Semaphore;
+use rand::Rng;
+use std::collections::HashMap;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+use url::Url;
+
+/// Limits and retry settings for the requests a client sends.
+#[derive(Clone, Debug)]
+pub struct RequestPolicy {
+    /// How many requests per second are sent to a server on average. Zero disables rate limiting.
+    pub requests_per_second: f64,
+
+    /// How many requests can be sent in a burst before the rate limit kicks in.
+    pub burst: u32,
+
+    /// How many requests can be in flight at the same time.
+    pub max_concurrency: usize,
+
+    /// How many times a failed GET request is retried. Other requests are never retried.
+    pub max_retries: u32,
+
+    /// The delay before the first retry. The delay doubles on every retry.
+    pub base_delay: Duration,
+
+    /// The maximum delay between retries.
+    pub max_delay: Duration,
+}
+
+impl Default for RequestPolicy {
+    fn default() -> Self {
+        Self {
+            requests_per_second: 5.0,
+            burst: 10,
+            max_concurrency: 4,
+            max_retries: 3,
+            base_delay: Duration::from_millis(250),
+            max_delay: Duration::from_secs(10),
+        }
+    }
+}
+
+impl RequestPolicy {
+    /// The delay before the given retry (zero-based), with full jitter: a random duration between
+    /// zero and the exponential backoff.
+    pub fn backoff(&self, retry: u32) -> Duration {
+        let backoff = self
+            .base_delay
+            .saturating_mul(2u32.saturating_pow(retry))
+            .min(self.max_delay);
+
+        backoff.mul_f64(rand::thread_rng().gen_range(0.0..=1.0))
+    }
+}
+
+/// Counters of what the policy layer has done.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct Counters {
+    /// Requests sent, including retries.
+    pub requests: u64,
+
+    /// Requests that were retried.
+    pub retries: u64,
+
+    /// Requests that failed even after retrying.
+    pub failures: u64,
+
+    /// Times a request had to wait for the rate limit.
+    pub throttled: u64,
+}
+
+struct TokenBucket {
+    tokens: f64,
+    updated: Instant,
+}
+
+impl TokenBucket {
+    fn new(burst: u32) -> Self {
+        Self {
+            tokens: burst as f64,
+            updated: Instant::now(),
+        }
+    }
+
+    /// Takes a token, or returns how long to wait until one is available.
+    fn take(&mut self, now: Instant, rate: f64, burst: u32) -> Option<Duration> {
+        let elapsed = now.saturating_duration_since(self.updated).as_secs_f64();
+        self.tokens = (self.tokens + elapsed * rate).min(burst as f64);
+        self.updated = now;
+
+        if self.tokens >= 1.0 {
+            self.tokens -= 1.0;
+            None
+        } else {
+            Some(Duration::from_secs_f64((1.0 - self.tokens) / rate))
+        }
+    }
+}
+
+/// Applies a request policy to the requests of a client.
+pub(crate) struct PolicyLayer {
+    policy: RequestPolicy,
+    timer: Arc<dyn Timer>,
+    buckets: Mutex<HashMap<String, TokenBucket>>,
+    semaphore: Semaphore,
+    requests: AtomicU64,
+    retries: AtomicU64,
+    failures: AtomicU64,
+    throttled: AtomicU64,
+}
+
+impl PolicyLayer {
+    pub fn new(policy: RequestPolicy, timer: Arc<dyn Timer>) -> Self {
+        Self {
+            semaphore: Semaphore::new(policy.max_concurrency.max(1)),
+            policy,
+            timer,
+            buckets: Mutex::new(HashMap::new()),
+            requests: AtomicU64::new(0),
+            retries: AtomicU64::new(0),
+            failures: AtomicU64::new(0),
+            throttled: AtomicU64::new(0),
+        }
+    }
+
+    pub fn policy(&self) -> &RequestPolicy {
+        &self.policy
+    }
+
+    pub fn timer(&self) -> Arc<dyn Timer> {
+        self.timer.clone()
+    }
+
+    pub fn counters(&self) -> Counters {
+        Counters {
+            requests: self.requests.load(Ordering::Relaxed),
+            retries: self.retries.load(Ordering::Relaxed),
+            failures: self.failures.load(Ordering::Relaxed),
+            throttled: self.throttled.load(Ordering::Relaxed),
+        }
+    }
+
+    async fn wait_for_token(&self, server: &str) {
+        if self.policy.requests_per_second <= 0.0 {
+            return;
+        }
+
+        loop {
+            let wait = {
+                let mut buckets = self.buckets.lock().unwrap_or_else(|e| e.into_inner());
+                let bucket = buckets
+                    .entry(server.into())
+                    .or_insert_with(|| TokenBucket::new(self.policy.burst.max(1)));
+
+                bucket.take(
+                    Instant::now(),
+                    self.policy.requests_per_second,
+                    self.policy.burst.max(1),
+                )
+            };
+
+            match wait {
+                None => return,
+                Some(wait) => {
+                    self.throttled.fetch_add(1, Ordering::Relaxed);
+                    self.timer.sleep(wait).await;
+                }
+            }
+        }
+    }
+
+    /// Sends a request. GET requests that fail with a retryable `TransportError`, 429 or a 5xx
+    /// status are retried. A request only counts towards `RequestPolicy::max_concurrency` while
+    /// it's being sent, not while it waits for a retry.
+    pub async fn execute<T: Transport>(
+        &self,
+        transport: &T,
+        request: Request,
+    ) -> anyhow::Result<Response> {
+        let server = Url::parse(&request.url)
+            .ok()
+            .and_then(|url| url.host_str().map(String::from))
+            .unwrap_or_default();
+        let retries = match request.method {
+            Method::Get => self.policy.max_retries,
+            Method::Post => 0,
+        };
+
+        let mut retry = 0;
+
+        loop {
+            self.wait_for_token(&server).await;
+            self.requests.fetch_add(1, Ordering::Relaxed);
+
+            if retry >= retries {
+                let _permit = self.semaphore.acquire().await;
+                return self.finish(transport.send(request).await);
+            }
+
+            let result = {
+                let _permit = self.semaphore.acquire().await;
+                transport.send(request.clone()).await
+            };
+
+            match result {
+                Ok(response) if !is_retryable_status(response.status) => return Ok(response),
+                Err(e) if !TransportError::is_retryable_error(&e) => return self.finish(Err(e)),
+                _ => {}
+            }
+
+            self.retries.fetch_add(1, Ordering::Relaxed);
+            self.timer.sleep(self.policy.backoff(retry)).await;
+            retry += 1;
+        }
+    }
+
+    fn finish(&self, result: anyhow::Result<Response>) -> anyhow::Result<Response> {
+        let failed = match &result {
+            Ok(response) => is_retryable_status(response.status),
+            Err(_) => true,
+        };
+
+        if failed {
+            self.failures.fetch_add(1, Ordering::Relaxed);
+        }
+
+        result
+    }
+}
+
+fn is_retryable_status(status: u16) -> bool {
+    status >= 500 || status == 429
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::transport::MemoryTransport;
+    use async_trait::async_trait;
+    use std::sync::atomic::AtomicUsize;
+
+    /// A transport whose every request fails.
+    struct FailingTransport {
+        retryable: bool,
+        sent: AtomicUsize,
+    }
+
+    #[async_trait]
+    impl Transport for FailingTransport {
+        async fn send(&self, _: Request) -> anyhow::Result<Response> {
+            self.sent.fetch_add(1, Ordering::Relaxed);
+
+            let error = anyhow::anyhow!("failed");
+            Err(match self.retryable {
+                true => TransportError::retryable(error),
+                false => TransportError::permanent(error),
+            }
+            .into())
+        }
+    }
+
+    #[tokio::test]
+    async fn retries_only_retryable_errors() {
+        let url = "https://wilma.example.com/overview";
+        let policy = RequestPolicy {
+            base_delay: Duration::from_millis(1),
+            max_retries: 2,
+            ..RequestPolicy::default()
+        };
+
+        for (retryable, sent) in [(true, 3), (false, 1)] {
+            let transport = FailingTransport {
+                retryable,
+                sent: AtomicUsize::new(0),
+            };
+            let layer = PolicyLayer::new(policy.clone(), crate::runtime::default_timer());
+
+            assert!(layer.execute(&transport, Request::get(url)).await.is_err());
+            assert_eq!(transport.sent.load(Ordering::Relaxed), sent);
+            assert_eq!(layer.counters().failures, 1);
+        }
+    }
+
+    #[tokio::test]
+    async fn releases_the_permit_while_waiting_for_a_retry() {
+        let failing = "https://wilma.example.com/overview";
+        let working = "https://wilma.example.com/messages/list";
+        let transport = MemoryTransport::new()
+            .with_response(Method::Get, failing, Response::new(503, ""))
+            .with_response(Method::Get, working, Response::new(200, "{}"));
+
+        let policy = RequestPolicy {
+            requests_per_second: 0.0,
+            max_concurrency: 1,
+            max_retries: 1,
+            base_delay: Duration::from_secs(3600),
+            max_delay: Duration::from_secs(3600),
+            ..RequestPolicy::default()
+        };
+        let layer = PolicyLayer::new(policy, crate::runtime::default_timer());
+
+        let waiting = layer.execute(&transport, Request::get(failing));
+        let other = layer.execute(&transport, Request::get(working));
+
+        // The first request is sent and starts waiting for its retry before the other one.
+        let response = tokio::time::timeout(Duration::from_secs(5), async {
+            tokio::select! {
+                biased;
+                _ = waiting => panic!("the retry shouldn't happen yet"),
+                response = other => response,
+            }
+        })
+        .await
+        .expect("the other request shouldn't wait for the retry");
+
+        assert_eq!(response.unwrap().status, 200);
+    }
+
+    #[test]
+    fn bucket_refills_over_time() {
+        let start = Instant::now();
+        let mut bucket = TokenBucket::new(2);
+
+        assert!(bucket.take(start, 1.0, 2).is_none());
+        assert!(bucket.take(start, 1.0, 2).is_none());
+        assert_eq!(bucket.take(start, 1.0, 2), Some(Duration::from_secs(1)));
+        assert!(bucket
+            .take(start + Duration::from_secs(1), 1.0, 2)
+            .is_none());
+    }
+
+    #[test]
+    fn backoff_is_bounded() {
+        let policy = RequestPolicy::default();
+
+        for retry in 0..40 {
+            assert!(policy.backoff(retry) <= policy.max_delay);
+        }
+    }
+
+    #[tokio::test]
+    async fn retries_server_errors() {
+        let url = "https://wilma.example.com/overview";
+        let transport = MemoryTransport::new()
+            .with_response(Method::Get, url, Response::new(503, ""))
+            .with_response(Method::Get, url, Response::new(200, "{}"));
+
+        let policy = RequestPolicy {
+            base_delay: Duration::from_millis(1),
+            ..RequestPolicy::default()
+        };
+        let layer = PolicyLayer::new(policy, crate::runtime::default_timer());
+
+        let response = layer.execute(&transport, Request::get(url)).await.unwrap();
+
+        assert_eq!(response.status, 200);
+        assert_eq!(
+            layer.counters(),
+            Counters {
+                requests: 2,
+                retries: 1,
+                failures: 0,
+                throttled: 0,
+            }
+        );
+    }
+
+    #[tokio::test]
+    async fn never_retries_posts() {
+        let url = "https://wilma.example.com/login";
+        let transport =
+            MemoryTransport::new().with_response(Method::Post, url, Response::new(503, ""));
+
+        let layer = PolicyLayer::new(RequestPolicy::default(), crate::runtime::default_timer());
+        let response = layer
+            .execute(&transport, Request::post_form(url, &[]))
+            .await
+            .unwrap();
+
+        assert_eq!(response.status, 503);
+        assert_eq!(transport.requests().len(), 1);
+        assert_eq!(layer.counters().failures, 1);
+    }
+}
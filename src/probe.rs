@@ -0,0 +1,234 @@
+use crate::{
+    client::{parse_json, send},
+    compat::ApiVersion,
+    parser::{login_page as parser, Page, SelectorOverrides},
+    policy::{PolicyLayer, RequestPolicy},
+    runtime,
+    transport::{Request, Transport},
+    utils,
+    wilma::IndexResponse,
+};
+use std::sync::Arc;
+
+/// A link to an external identity provider on the login page.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SsoProvider {
+    pub name: String,
+
+    /// The URL of the link, for `Client::start_sso`.
+    pub url: String,
+}
+
+/// What a Wilma server supports, as far as can be told without logging in.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ServerInfo {
+    /// The server URL, for example `https://turku.inschool.fi`.
+    pub server: String,
+
+    /// The API version from `index_json`. None if the server didn't answer it.
+    pub api_version: Option<u32>,
+
+    /// The `LoginResult` from `index_json`.
+    pub login_result: Option<String>,
+
+    /// Whether users can log in with a username and a password.
+    pub password_login: bool,
+
+    /// External identity providers users can log in with.
+    pub sso_providers: Vec<SsoProvider>,
+
+    /// Whether the login page mentions two-step verification. Accounts may still use it if this
+    /// is false; see `Login::MfaRequired`.
+    pub mfa_hint: bool,
+
+    /// Whether the server is down for maintenance.
+    pub maintenance: bool,
+
+    /// The maintenance notice, if the server showed one.
+    pub maintenance_message: Option<String>,
+
+    /// The languages the user interface is available in, as language codes like `fi`.
+    pub languages: Vec<String>,
+}
+
+impl ServerInfo {
+    /// Whether the client can talk to the API version of the server. See `ApiVersion`.
+    pub fn is_supported(&self) -> bool {
+        self.api_version
+            .is_some_and(|version| ApiVersion::new(version).is_ok())
+    }
+}
+
+/// Inspects what a server supports before asking the user for credentials. See `ServerInfo`.
+#[cfg(feature = "reqwest")]
+pub async fn probe(server: &str) -> anyhow::Result<ServerInfo> {
+    probe_with(&crate::transport::DefaultTransport::new()?, server).await
+}
+
+/// Probes a server using the given transport. See `probe`.
+pub async fn probe_with<T: Transport>(transport: &T, server: &str) -> anyhow::Result<ServerInfo> {
+    probe_with_overrides(transport, server, &SelectorOverrides::default()).await
+}
+
+/// Probes a server like `probe_with`, parsing the login page with selector overrides.
+#[tracing::instrument(skip(transport, overrides))]
+pub async fn probe_with_overrides<T: Transport>(
+    transport: &T,
+    server: &str,
+    overrides: &SelectorOverrides,
+) -> anyhow::Result<ServerInfo> {
+    // A server under maintenance answers 503, which is worth reporting rather than retrying.
+    let policy = RequestPolicy {
+        max_retries: 0,
+        ..RequestPolicy::default()
+    };
+    let policy = PolicyLayer::new(policy, runtime::default_timer());
+    let url = utils::verify_url(&server);
+
+    let index = send(
+        transport,
+        &policy,
+        Request::get(format!("{}/index_json", url)),
+    )
+    .await?;
+    let index: Option<IndexResponse> = match index.is_success() {
+        true => parse_json("index_json", &index.text()).ok(),
+        false => None,
+    };
+
+    let page = send(transport, &policy, Request::get(url.clone())).await?;
+    let unavailable = page.status == 503;
+    let selectors = overrides.resolve(index.as_ref().map(|i| i.api_version()));
+    let page = Page::with_selectors(&page.text(), Arc::new(selectors));
+
+    let maintenance_message = parser::parse_maintenance(&page);
+
+    Ok(ServerInfo {
+        api_version: index.as_ref().map(|i| i.api_version()),
+        login_result: index.as_ref().map(|i| i.login_result().clone()),
+        password_login: !unavailable && parser::parse_password_login(&page),
+        sso_providers: parser::parse_sso_providers(&page)
+            .into_iter()
+            .map(|(name, url)| SsoProvider { name, url })
+            .collect(),
+        mfa_hint: parser::parse_mfa_hint(&page),
+        maintenance: unavailable || maintenance_message.is_some(),
+        maintenance_message,
+        languages: parser::parse_languages(&page),
+        server: url,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::transport::{MemoryTransport, Method, Response};
+
+    const LOGIN_PAGE: &str = r#"<!DOCTYPE html>
+        <html lang="fi">
+          <body>
+            <form action="/login" method="post">
+              <input name="Login" />
+              <input type="password" name="Password" />
+            </form>
+            <a href="/saml/login/1">Kirjaudu MPASSid:llä</a>
+            <a href="/oidc/login?provider=2">Azure AD</a>
+            <p class="login-mfa-info" data-mfa="1">Kaksivaiheinen tunnistautuminen</p>
+            <a href="/?langid=2" hreflang="sv-FI">På svenska</a>
+            <a href="/?langid=3" hreflang="en">In English</a>
+          </body>
+        </html>"#;
+
+    #[tokio::test]
+    async fn inspects_the_login_page() {
+        let transport = MemoryTransport::new()
+            .with_response(
+                Method::Get,
+                "https://wilma.example.com/index_json",
+                Response::new(
+                    200,
+                    r#"{ "LoginResult": "Failed", "SessionID": "abc", "ApiVersion": 13 }"#,
+                ),
+            )
+            .with_response(
+                Method::Get,
+                "https://wilma.example.com",
+                Response::new(200, LOGIN_PAGE),
+            );
+
+        let info = probe_with(&transport, "wilma.example.com").await.unwrap();
+
+        assert_eq!(info.api_version, Some(13));
+        assert!(info.is_supported());
+        assert!(info.password_login);
+        assert_eq!(
+            info.sso_providers,
+            vec![
+                SsoProvider {
+                    name: "Kirjaudu MPASSid:llä".into(),
+                    url: "/saml/login/1".into()
+                },
+                SsoProvider {
+                    name: "Azure AD".into(),
+                    url: "/oidc/login?provider=2".into()
+                },
+            ]
+        );
+        assert!(info.mfa_hint);
+        assert!(!info.maintenance);
+        assert_eq!(info.languages, vec!["fi", "sv", "en"]);
+    }
+
+    #[tokio::test]
+    async fn detects_maintenance() {
+        let transport = MemoryTransport::new().with_response(
+            Method::Get,
+            "https://wilma.example.com",
+            Response::new(
+                503,
+                r#"<div class="maintenance-notice">Wilma is being updated.</div>"#,
+            ),
+        );
+
+        let info = probe_with(&transport, "wilma.example.com").await.unwrap();
+
+        assert_eq!(info.api_version, None);
+        assert!(!info.password_login);
+        assert!(info.maintenance);
+        assert_eq!(
+            info.maintenance_message.as_deref(),
+            Some("Wilma is being updated.")
+        );
+    }
+
+    #[tokio::test]
+    async fn applies_selector_overrides() {
+        let transport = MemoryTransport::new().with_response(
+            Method::Get,
+            "https://wilma.example.com",
+            Response::new(
+                200,
+                r##"<a class="idp" data-url="/saml/login/1" href="#">MPASSid</a>"##,
+            ),
+        );
+
+        let overrides = SelectorOverrides::from_json(
+            r#"{ "default": { "sso_provider": { "selector": "a.idp", "attribute": "data-url" } } }"#,
+        )
+        .unwrap();
+
+        let info = probe_with(&transport, "wilma.example.com").await.unwrap();
+        assert!(info.sso_providers.is_empty());
+
+        let info = probe_with_overrides(&transport, "wilma.example.com", &overrides)
+            .await
+            .unwrap();
+        assert_eq!(
+            info.sso_providers,
+            vec![SsoProvider {
+                name: "MPASSid".into(),
+                url: "/saml/login/1".into()
+            }]
+        );
+    }
+}
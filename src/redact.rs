@@ -0,0 +1,97 @@
+/// Keys whose values are secrets and must never end up in logs. Matched case-insensitively.
+pub const SECRET_KEYS: &[&str] = &["Password", "SESSIONID", "Wilma2SID", "formkey"];
+
+/// What redacted values are replaced with.
+pub const REDACTED: &str = "[REDACTED]";
+
+/// Replaces the values of secret keys in URLs, form bodies, cookie headers and JSON with
+/// `[REDACTED]`. For example `Login=a&Password=b` becomes `Login=a&Password=[REDACTED]`.
+pub fn redact(text: &str) -> String {
+    SECRET_KEYS
+        .iter()
+        .fold(text.to_string(), |text, key| redact_key(&text, key))
+}
+
+fn redact_key(text: &str, key: &str) -> String {
+    // ASCII lowercasing keeps byte offsets intact, so indices can be used on both strings.
+    let lowercase = text.to_ascii_lowercase();
+    let key = key.to_ascii_lowercase();
+
+    let mut result = String::with_capacity(text.len());
+    let mut position = 0;
+
+    while let Some(index) = lowercase[position..].find(&key) {
+        let key_end = position + index + key.len();
+        result.push_str(&text[position..key_end]);
+
+        // Skip the separator: `=`, `:`, `":` or an HTML `" value=`, with optional whitespace and
+        // an opening quote.
+        let rest = text[key_end..].trim_start_matches('"');
+        let separator = match rest.strip_prefix(['=', ':']) {
+            Some(separator) => separator,
+            None => match rest.trim_start().strip_prefix("value=") {
+                Some(separator) => separator,
+                None => {
+                    position = key_end;
+                    continue;
+                }
+            },
+        };
+        let separator = separator.trim_start().trim_start_matches('"');
+        let value_start = text.len() - separator.len();
+
+        let value_length = separator
+            .find(|c: char| matches!(c, '&' | ';' | '"' | ',' | '}') || c.is_whitespace())
+            .unwrap_or(separator.len());
+
+        result.push_str(&text[key_end..value_start]);
+
+        if value_length > 0 {
+            result.push_str(REDACTED);
+        }
+
+        position = value_start + value_length;
+    }
+
+    result.push_str(&text[position..]);
+    result
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn redacts_secrets() {
+        assert_eq!(
+            redact("Login=user&Password=hunter2&SESSIONID=abc"),
+            "Login=user&Password=[REDACTED]&SESSIONID=[REDACTED]"
+        );
+        assert_eq!(
+            redact("Wilma2SID=abc; path=/"),
+            "Wilma2SID=[REDACTED]; path=/"
+        );
+        assert_eq!(
+            redact(r#"{"formkey": "student:1:abc", "Name": "x"}"#),
+            r#"{"formkey": "[REDACTED]", "Name": "x"}"#
+        );
+        assert_eq!(
+            redact(r#"<input name="formkey" value="student:1:abc" />"#),
+            r#"<input name="formkey" value="[REDACTED]" />"#
+        );
+        assert_eq!(
+            redact("/!01/schedule?FormKey=student:1:abc"),
+            "/!01/schedule?FormKey=[REDACTED]"
+        );
+    }
+
+    #[test]
+    fn leaves_other_text_alone() {
+        assert_eq!(redact("passwords are secret"), "passwords are secret");
+        assert_eq!(redact("Password="), "Password=");
+        assert_eq!(
+            redact("schedule/export/students/1"),
+            "schedule/export/students/1"
+        );
+    }
+}
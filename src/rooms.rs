@@ -0,0 +1,235 @@
+use crate::{
+    transport::Transport,
+    wilma::schedule::{
+        reservation::{Time, TimeSpan, Weekday},
+        Room, Schedule,
+    },
+    Client, Error as WilmaError,
+};
+use chrono::{Datelike, NaiveDate};
+use futures::stream::{self, StreamExt};
+
+/// How many room schedules are fetched at the same time by default.
+pub const DEFAULT_CONCURRENCY: usize = 4;
+
+/// A room that is in use during the queried time window.
+#[derive(Debug, Clone)]
+pub struct BusyRoom {
+    pub room: Room,
+
+    /// The time when the room becomes free.
+    pub free_at: Time,
+}
+
+/// The result of a free room query.
+#[derive(Debug, Clone, Default)]
+pub struct FreeRooms {
+    /// Rooms that are free during the whole time window.
+    pub free: Vec<Room>,
+
+    /// Rooms that are in use at some point during the time window.
+    pub busy: Vec<BusyRoom>,
+
+    /// Rooms whose schedule couldn't be fetched, for example because it isn't visible.
+    pub unknown: Vec<Room>,
+}
+
+/// Finds the rooms that are free on `date` between `start` and `end`. The rooms are discovered
+/// from the reservations in the user's own schedule, and at most `concurrency` room schedules are
+/// fetched at the same time. Rooms are free on dates outside the terms of their schedules.
+pub async fn find_free_rooms<T: Transport>(
+    client: &Client<T>,
+    date: NaiveDate,
+    start: &Time,
+    end: &Time,
+    concurrency: usize,
+) -> anyhow::Result<FreeRooms> {
+    let schedule = client.get_user_schedule().await?;
+    let rooms: Vec<Room> = schedule.rooms().into_iter().cloned().collect();
+
+    let mut results = stream::iter(rooms)
+        .map(|room| async move {
+            let room_schedule = client.get_schedule_of_room(&room).await;
+            (room, room_schedule)
+        })
+        .buffer_unordered(concurrency.max(1));
+
+    let mut free_rooms = FreeRooms::default();
+
+    while let Some((room, room_schedule)) = results.next().await {
+        let room_schedule = match room_schedule {
+            Ok(room_schedule) => room_schedule,
+            // Every other room would fail the same way.
+            Err(e) if matches!(e.downcast_ref(), Some(WilmaError::SessionExpired)) => {
+                return Err(e)
+            }
+            Err(e) => {
+                tracing::warn!(room = room.id(), error = %e, "failed to get room schedule");
+                free_rooms.unknown.push(room);
+                continue;
+            }
+        };
+
+        match free_at(&room_schedule, date, start, end) {
+            None => free_rooms.free.push(room),
+            Some(free_at) => free_rooms.busy.push(BusyRoom { room, free_at }),
+        }
+    }
+
+    free_rooms.free.sort_by_key(|r| r.id());
+    free_rooms.busy.sort_by_key(|r| r.room.id());
+    free_rooms.unknown.sort_by_key(|r| r.id());
+
+    Ok(free_rooms)
+}
+
+/// Returns none if the schedule has no reservations between `start` and `end` on the given
+/// date, otherwise the first time after the first of those reservations when there is no
+/// reservation going on. Like `Schedule::expand`, there are no reservations on dates that no
+/// term of the schedule contains.
+pub fn free_at(schedule: &Schedule, date: NaiveDate, start: &Time, end: &Time) -> Option<Time> {
+    if !schedule.terms().iter().any(|t| t.contains(date)) {
+        return None;
+    }
+
+    let weekday = Weekday::from(date.weekday());
+    let reservations: Vec<TimeSpan> = schedule
+        .reservations()
+        .iter()
+        .filter(|r| r.weekday == weekday)
+        .map(|r| r.span())
+        .collect();
+
+    let window = TimeSpan {
+        start: *start,
+        end: *end,
+    };
+
+    let first = reservations
+        .iter()
+        .filter(|r| r.overlaps(&window))
+        .min_by_key(|r| r.start)?;
+
+    // Follow back-to-back reservations until there's a gap.
+    let mut free_at = first.end;
+    while let Some(reservation) = reservations.iter().find(|r| r.contains(free_at)) {
+        free_at = reservation.end;
+    }
+
+    Some(free_at)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        transport::{MemoryTransport, Method, Response},
+        Secret, Session,
+    };
+
+    fn time(data: &str) -> Time {
+        data.parse().unwrap()
+    }
+
+    /// Monday 2022-03-14, in the spring term of the schedule.
+    fn monday() -> NaiveDate {
+        NaiveDate::from_ymd_opt(2022, 3, 14).unwrap()
+    }
+
+    fn schedule() -> Schedule {
+        serde_json::from_str(
+            r#"{
+                "Terms": [{ "Name": "Kevät", "StartDate": "2022-01-10", "EndDate": "2022-06-04" }],
+                "Schedule": [
+                    { "Day": 1, "Class": "", "Color": null, "Start": "08:15", "End": "09:30",
+                      "Groups": [], "ScheduleID": 1, "ReservationID": 1 },
+                    { "Day": 1, "Class": "", "Color": null, "Start": "09:30", "End": "10:45",
+                      "Groups": [], "ScheduleID": 1, "ReservationID": 2 },
+                    { "Day": 1, "Class": "", "Color": null, "Start": "12:00", "End": "13:15",
+                      "Groups": [], "ScheduleID": 1, "ReservationID": 3 }
+                ]
+            }"#,
+        )
+        .unwrap()
+    }
+
+    #[test]
+    fn room_is_free() {
+        let free = free_at(&schedule(), monday(), &time("10:45"), &time("12:00"));
+        assert!(free.is_none());
+
+        let tuesday = monday().succ_opt().unwrap();
+        let free = free_at(&schedule(), tuesday, &time("08:00"), &time("16:00"));
+        assert!(free.is_none());
+    }
+
+    #[test]
+    fn room_is_free_outside_the_terms() {
+        let summer = NaiveDate::from_ymd_opt(2022, 7, 4).unwrap();
+        let free = free_at(&schedule(), summer, &time("08:00"), &time("16:00"));
+        assert!(free.is_none());
+    }
+
+    #[test]
+    fn room_becomes_free_after_back_to_back_reservations() {
+        let free = free_at(&schedule(), monday(), &time("09:00"), &time("10:00")).unwrap();
+        assert_eq!(free.fmt(), "10:45");
+
+        let free = free_at(&schedule(), monday(), &time("11:00"), &time("12:30")).unwrap();
+        assert_eq!(free.fmt(), "13:15");
+    }
+
+    #[tokio::test]
+    async fn skips_rooms_whose_schedule_fails() {
+        const BASE_URL: &str = "https://wilma.example.com/!0123456/";
+
+        let room = |id: u32| {
+            format!(
+                r#"{{ "Caption": "{id}", "Id": {id}, "LongCaption": "Luokka {id}",
+                      "ScheduleVisible": true }}"#
+            )
+        };
+        let user_schedule = format!(
+            r#"{{ "Terms": [], "Schedule": [
+                {{ "Day": 1, "Class": "7A", "Color": null, "Start": "08:15", "End": "09:30",
+                   "ScheduleID": 1, "ReservationID": 1,
+                   "Groups": [{{ "Caption": "MA", "Class": "7A", "CourseId": 1,
+                                 "FullCaption": "Matematiikka", "Id": 1, "ShortCaption": "MA",
+                                 "Rooms": [{}, {}], "Teachers": [] }}] }}
+            ] }}"#,
+            room(101),
+            room(102),
+        );
+
+        let transport = MemoryTransport::new()
+            .with_response(
+                Method::Get,
+                BASE_URL,
+                Response::new(200, include_str!("parser/fixtures/student_front_page.html")),
+            )
+            .with_response(
+                Method::Get,
+                format!("{}schedule/export/students/123456", BASE_URL),
+                Response::new(200, user_schedule),
+            )
+            .with_response(
+                Method::Get,
+                format!("{}schedule/export/rooms/101", BASE_URL),
+                Response::new(200, r#"{ "Terms": [], "Schedule": [] }"#),
+            );
+
+        let session = Session {
+            base_url: BASE_URL.into(),
+            id: Secret::new("session"),
+        };
+        let client = Client::resume_with(transport, session);
+
+        let rooms = find_free_rooms(&client, monday(), &time("08:00"), &time("16:00"), 2)
+            .await
+            .unwrap();
+
+        assert_eq!(rooms.free[0].id(), 101);
+        assert!(rooms.busy.is_empty());
+        assert_eq!(rooms.unknown[0].id(), 102);
+    }
+}
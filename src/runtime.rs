@@ -0,0 +1,143 @@
+//! Abstractions over async runtimes, so the crate works on any executor, and over the clock, so
+//! time-dependent logic can be tested.
+
+use chrono::{Local, NaiveDateTime};
+use futures::future::BoxFuture;
+use std::sync::Arc;
+use std::time::Duration;
+
+/// Something that can wait for a duration. Used for rate limiting, retries and polling.
+pub trait Timer: Send + Sync {
+    fn sleep(&self, duration: Duration) -> BoxFuture<'static, ()>;
+}
+
+/// A timer that works with every executor. It runs its own background thread for waking up
+/// sleeping tasks.
+#[derive(Clone, Copy, Debug, Default)]
+pub struct DefaultTimer;
+
+impl Timer for DefaultTimer {
+    fn sleep(&self, duration: Duration) -> BoxFuture<'static, ()> {
+        Box::pin(futures_timer::Delay::new(duration))
+    }
+}
+
+/// A timer backed by tokio. Must be used from within a tokio runtime.
+#[cfg(feature = "tokio")]
+#[derive(Clone, Copy, Debug, Default)]
+pub struct TokioTimer;
+
+#[cfg(feature = "tokio")]
+impl Timer for TokioTimer {
+    fn sleep(&self, duration: Duration) -> BoxFuture<'static, ()> {
+        Box::pin(tokio::time::sleep(duration))
+    }
+}
+
+/// The timer used when none is given.
+pub fn default_timer() -> Arc<dyn Timer> {
+    Arc::new(DefaultTimer)
+}
+
+/// Something that tells the current date and time. Used for "now and next" queries, see
+/// `Schedule::now`.
+pub trait Clock: Send + Sync {
+    fn now(&self) -> NaiveDateTime;
+}
+
+/// The system clock, in the local time zone of the machine.
+#[derive(Clone, Copy, Debug, Default)]
+pub struct SystemClock;
+
+impl Clock for SystemClock {
+    fn now(&self) -> NaiveDateTime {
+        Local::now().naive_local()
+    }
+}
+
+/// A clock that is stopped at a given time, for tests and for showing another moment.
+#[derive(Clone, Copy, Debug)]
+pub struct FixedClock(pub NaiveDateTime);
+
+impl Clock for FixedClock {
+    fn now(&self) -> NaiveDateTime {
+        self.0
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        transport::{MemoryTransport, Method, Response},
+        Client, Credentials,
+    };
+    use chrono::NaiveDate;
+    use futures::executor::block_on;
+    use std::time::Instant;
+
+    #[test]
+    fn default_timer_works_without_tokio() {
+        let start = Instant::now();
+        block_on(DefaultTimer.sleep(Duration::from_millis(20)));
+
+        assert!(start.elapsed() >= Duration::from_millis(20));
+    }
+
+    #[test]
+    fn fixed_clock_doesnt_move() {
+        let moment = NaiveDate::from_ymd_opt(2022, 3, 14)
+            .unwrap()
+            .and_hms_opt(9, 0, 0)
+            .unwrap();
+        let clock: &dyn Clock = &FixedClock(moment);
+
+        assert_eq!(clock.now(), moment);
+        assert_eq!(clock.now(), moment);
+    }
+
+    #[test]
+    fn client_works_without_tokio() {
+        let transport = MemoryTransport::new()
+            .with_response(
+                Method::Get,
+                "https://wilma.example.com/index_json",
+                Response::new(
+                    200,
+                    r#"{ "LoginResult": "Failed", "SessionID": "abc", "ApiVersion": 13 }"#,
+                ),
+            )
+            .with_response(
+                Method::Post,
+                "https://wilma.example.com/login",
+                Response::new(303, "").with_header("Set-Cookie", "Wilma2SID=sid; path=/"),
+            )
+            .with_response(
+                Method::Get,
+                "https://wilma.example.com",
+                Response::new(
+                    200,
+                    r#"<a class="text-style-link" href="/!0123456/">Oppilas</a>"#,
+                ),
+            )
+            .with_response(
+                Method::Get,
+                "https://wilma.example.com/!0123456/",
+                Response::new(200, include_str!("parser/fixtures/student_front_page.html")),
+            );
+
+        let credentials = Credentials {
+            username: "user",
+            password: "hunter2",
+            server: "wilma.example.com",
+        };
+
+        let user = block_on(async {
+            let client = Client::login_with(transport, credentials).await?;
+            client.get_user_profile().await
+        })
+        .unwrap();
+
+        assert_eq!(user.name(), "Meikäläinen Matti");
+    }
+}
@@ -0,0 +1,96 @@
+use crate::{redact::REDACTED, Credentials};
+use std::fmt;
+use zeroize::{Zeroize, ZeroizeOnDrop};
+
+/// A string that must not leak, like a password or a session ID. It's redacted when formatted
+/// with `Debug` and wiped from memory when dropped.
+#[derive(Clone, PartialEq, Eq, Zeroize, ZeroizeOnDrop)]
+pub struct Secret(String);
+
+impl Secret {
+    pub fn new<T: Into<String>>(secret: T) -> Self {
+        Self(secret.into())
+    }
+
+    /// The secret itself. Be careful not to log it.
+    pub fn expose(&self) -> &str {
+        &self.0
+    }
+}
+
+impl fmt::Debug for Secret {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}", REDACTED)
+    }
+}
+
+impl From<String> for Secret {
+    fn from(secret: String) -> Self {
+        Self(secret)
+    }
+}
+
+impl From<&str> for Secret {
+    fn from(secret: &str) -> Self {
+        Self(secret.into())
+    }
+}
+
+/// Credentials that own their data, for keeping them around between logins. The password is a
+/// `Secret`.
+#[derive(Clone, Debug, PartialEq, Eq, Zeroize, ZeroizeOnDrop)]
+pub struct OwnedCredentials {
+    pub username: String,
+    pub password: Secret,
+    pub server: String,
+}
+
+impl OwnedCredentials {
+    pub fn new<U, P, S>(username: U, password: P, server: S) -> Self
+    where
+        U: Into<String>,
+        P: Into<Secret>,
+        S: Into<String>,
+    {
+        Self {
+            username: username.into(),
+            password: password.into(),
+            server: server.into(),
+        }
+    }
+
+    /// Borrows the credentials for `Client::login`.
+    pub fn as_credentials(&self) -> Credentials<'_> {
+        Credentials {
+            username: &self.username,
+            password: self.password.expose(),
+            server: &self.server,
+        }
+    }
+}
+
+impl From<Credentials<'_>> for OwnedCredentials {
+    fn from(credentials: Credentials<'_>) -> Self {
+        Self::new(
+            credentials.username,
+            credentials.password,
+            credentials.server,
+        )
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn debug_is_redacted() {
+        let credentials = OwnedCredentials::new("user", "hunter2", "wilma.example.com");
+        let borrowed = credentials.as_credentials();
+
+        for debug in [format!("{:?}", credentials), format!("{:?}", borrowed)] {
+            assert!(!debug.contains("hunter2"));
+            assert!(debug.contains("user"));
+        }
+    }
+}
@@ -0,0 +1,479 @@
+use crate::{
+    compat::Endpoint,
+    parser,
+    transport::Transport,
+    wilma::{schedule::Term, Exam, Group, Message, Note, Reservation, Schedule, User},
+    Client,
+};
+use chrono::{DateTime, Utc};
+use rusqlite::{params, types::Value as SqlValue, Connection, OptionalExtension};
+use serde::de::DeserializeOwned;
+use serde_json::{json, Value};
+use std::path::Path;
+use std::time::Duration;
+
+pub mod schema;
+
+/// How old the user profile and the schedule may be before they are fetched again by default.
+/// They rarely change, unlike exams, messages and notes which are fetched on every sync.
+pub const DEFAULT_SCHEDULE_MAX_AGE: Duration = Duration::from_secs(24 * 60 * 60);
+
+/// The number of rows changed in a table during a sync.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct Changes {
+    pub inserted: usize,
+    pub updated: usize,
+    pub deleted: usize,
+}
+
+/// What a sync changed in the mirror.
+#[derive(Debug, Clone, Default)]
+pub struct SyncReport {
+    /// Whether the user profile and the schedule were fetched. They are skipped if they are
+    /// newer than the schedule max age.
+    pub schedule_synced: bool,
+    pub terms: Changes,
+    pub reservations: Changes,
+    pub exams: Changes,
+    pub groups: Changes,
+    pub messages: Changes,
+    pub notes: Changes,
+}
+
+/// A local SQLite mirror of a user's Wilma data.
+///
+/// Everything except the user profile is stored as the JSON Wilma sent, keyed by Wilma's own IDs,
+/// so the crate's types can be read back without touching the network.
+pub struct Store {
+    connection: Connection,
+    schedule_max_age: Duration,
+}
+
+impl Store {
+    /// Opens (or creates) the database at the given path and migrates it to the latest schema.
+    pub fn open<P: AsRef<Path>>(path: P) -> anyhow::Result<Self> {
+        Self::from_connection(Connection::open(path)?)
+    }
+
+    /// Creates a database that only lives in memory.
+    pub fn in_memory() -> anyhow::Result<Self> {
+        Self::from_connection(Connection::open_in_memory()?)
+    }
+
+    fn from_connection(mut connection: Connection) -> anyhow::Result<Self> {
+        schema::migrate(&mut connection)?;
+
+        Ok(Self {
+            connection,
+            schedule_max_age: DEFAULT_SCHEDULE_MAX_AGE,
+        })
+    }
+
+    /// Sets how old the user profile and the schedule may be before they are fetched again.
+    pub fn with_schedule_max_age(mut self, max_age: Duration) -> Self {
+        self.schedule_max_age = max_age;
+        self
+    }
+
+    /// Fetches the user's data and upserts it into the mirror. Rows that no longer exist in
+    /// Wilma are deleted, and rows that didn't change aren't touched.
+    ///
+    /// Wilma can't be asked for only what changed, so exams, groups, messages and notes are
+    /// fetched in full on every sync. The user profile and the schedule are only fetched when
+    /// they're older than the schedule max age.
+    pub async fn sync<T: Transport>(&mut self, client: &Client<T>) -> anyhow::Result<SyncReport> {
+        let now = Utc::now();
+        let mut report = SyncReport::default();
+
+        let schedule_age = self
+            .last_sync("schedule")?
+            .and_then(|t| (now - t).to_std().ok());
+
+        if schedule_age.is_none_or(|age| age >= self.schedule_max_age) {
+            let user = client.get_user_profile().await?;
+            self.save_user(&user)?;
+
+            let path = format!("schedule/export/students/{}", user.user_id());
+            let mut schedule = client.get_json(&path).await?;
+
+            report.terms =
+                self.replace::<Term>("terms", "name", "Name", take_list(&mut schedule, "Terms")?)?;
+            report.reservations = self.replace::<Reservation>(
+                "reservations",
+                "reservation_id",
+                "ReservationID",
+                take_list(&mut schedule, "Schedule")?,
+            )?;
+
+            self.mark_synced("schedule", now)?;
+            report.schedule_synced = true;
+        }
+
+        let mut overview = client.get_endpoint(Endpoint::Overview).await?;
+        report.exams = self.replace::<Exam>(
+            "exams",
+            "exam_id",
+            "ExamId",
+            take_list(&mut overview, "Exams")?,
+        )?;
+
+        // Wilma leaves the groups out of the overview of users who aren't in any.
+        let groups = match overview.get_mut("Groups") {
+            Some(groups) => groups.take(),
+            None => Value::Array(Vec::new()),
+        };
+        report.groups = self.replace::<Group>("course_groups", "course_id", "CourseId", groups)?;
+
+        let mut messages = client.get_endpoint(Endpoint::Messages).await?;
+        report.messages = self.replace::<Message>(
+            "messages",
+            "id",
+            "Id",
+            take_list(&mut messages, "Messages")?,
+        )?;
+
+        let mut notes = client.get_endpoint(Endpoint::Notes).await?;
+        report.notes =
+            self.replace::<Note>("notes", "id", "Id", take_list(&mut notes, "Notes")?)?;
+
+        self.mark_synced("overview", now)?;
+
+        Ok(report)
+    }
+
+    /// The time of the last successful sync of a resource (`"schedule"` or `"overview"`).
+    pub fn last_sync(&self, resource: &str) -> anyhow::Result<Option<DateTime<Utc>>> {
+        let timestamp: Option<i64> = self
+            .connection
+            .query_row(
+                "SELECT synced_at FROM sync_state WHERE resource = ?1",
+                [resource],
+                |row| row.get(0),
+            )
+            .optional()?;
+
+        Ok(timestamp.and_then(|t| DateTime::from_timestamp(t, 0)))
+    }
+
+    fn mark_synced(&self, resource: &str, time: DateTime<Utc>) -> anyhow::Result<()> {
+        self.connection.execute(
+            "INSERT INTO sync_state (resource, synced_at) VALUES (?1, ?2)
+             ON CONFLICT(resource) DO UPDATE SET synced_at = excluded.synced_at",
+            params![resource, time.timestamp()],
+        )?;
+
+        Ok(())
+    }
+
+    fn save_user(&mut self, user: &User) -> anyhow::Result<()> {
+        // Only keep the user type and ID from the formkey, the rest of it is a session token.
+        let formkey: Vec<&str> = user.formkey().splitn(3, ':').take(2).collect();
+
+        let transaction = self.connection.transaction()?;
+
+        // The other tables only mirror the last synced user, so the users table does too.
+        transaction.execute("DELETE FROM users WHERE user_id != ?1", [user.user_id()])?;
+        transaction.execute(
+            "INSERT INTO users (user_id, name, school, formkey) VALUES (?1, ?2, ?3, ?4)
+             ON CONFLICT(user_id) DO UPDATE
+             SET name = excluded.name, school = excluded.school, formkey = excluded.formkey",
+            params![
+                user.user_id(),
+                user.name(),
+                user.school(),
+                format!("{}:", formkey.join(":"))
+            ],
+        )?;
+
+        transaction.commit()?;
+        Ok(())
+    }
+
+    /// Replaces the contents of a table with the given JSON list. Every item must deserialize
+    /// into `T`, so the mirror can always be read back.
+    fn replace<T: DeserializeOwned>(
+        &mut self,
+        table: &str,
+        key_column: &str,
+        key_field: &str,
+        items: Value,
+    ) -> anyhow::Result<Changes> {
+        let items = match items {
+            Value::Array(items) => items,
+            _ => anyhow::bail!("Expected a list of {} from Wilma.", table),
+        };
+
+        let transaction = self.connection.transaction()?;
+        let mut changes = Changes::default();
+        let mut keys = Vec::new();
+
+        for item in items {
+            serde_json::from_value::<T>(item.clone())?;
+
+            let key = match item.get(key_field) {
+                Some(Value::Number(n)) if n.is_i64() => SqlValue::Integer(n.as_i64().unwrap()),
+                Some(Value::String(s)) => SqlValue::Text(s.clone()),
+                _ => anyhow::bail!("An item in {} doesn't have a valid {}.", table, key_field),
+            };
+            let data = item.to_string();
+
+            let existing: Option<String> = transaction
+                .query_row(
+                    &format!("SELECT data FROM {} WHERE {} = ?1", table, key_column),
+                    [&key],
+                    |row| row.get(0),
+                )
+                .optional()?;
+
+            match existing {
+                None => {
+                    transaction.execute(
+                        &format!(
+                            "INSERT INTO {} ({}, data) VALUES (?1, ?2)",
+                            table, key_column
+                        ),
+                        params![key, data],
+                    )?;
+                    changes.inserted += 1;
+                }
+                Some(existing) if existing != data => {
+                    transaction.execute(
+                        &format!("UPDATE {} SET data = ?2 WHERE {} = ?1", table, key_column),
+                        params![key, data],
+                    )?;
+                    changes.updated += 1;
+                }
+                Some(_) => {}
+            }
+
+            keys.push(key);
+        }
+
+        let existing_keys: Vec<SqlValue> = transaction
+            .prepare(&format!("SELECT {} FROM {}", key_column, table))?
+            .query_map([], |row| row.get(0))?
+            .collect::<Result<_, _>>()?;
+
+        for key in existing_keys.iter().filter(|k| !keys.contains(k)) {
+            transaction.execute(
+                &format!("DELETE FROM {} WHERE {} = ?1", table, key_column),
+                [key],
+            )?;
+            changes.deleted += 1;
+        }
+
+        transaction.commit()?;
+        Ok(changes)
+    }
+
+    fn load(&self, table: &str, key_column: &str) -> anyhow::Result<Vec<Value>> {
+        let rows: Vec<String> = self
+            .connection
+            .prepare(&format!(
+                "SELECT data FROM {} ORDER BY {}",
+                table, key_column
+            ))?
+            .query_map([], |row| row.get(0))?
+            .collect::<Result<_, _>>()?;
+
+        Ok(rows
+            .iter()
+            .map(|r| serde_json::from_str(r))
+            .collect::<Result<_, _>>()?)
+    }
+
+    fn load_as<T: DeserializeOwned>(
+        &self,
+        table: &str,
+        key_column: &str,
+    ) -> anyhow::Result<Vec<T>> {
+        Ok(self
+            .load(table, key_column)?
+            .into_iter()
+            .map(serde_json::from_value)
+            .collect::<Result<_, _>>()?)
+    }
+
+    /// The mirrored user profile. The session part of the formkey isn't stored.
+    pub fn user(&self) -> anyhow::Result<Option<User>> {
+        Ok(self
+            .connection
+            .query_row(
+                "SELECT name, school, formkey FROM users ORDER BY rowid DESC LIMIT 1",
+                [],
+                |row| Ok(User::new(row.get(0)?, row.get(1)?, row.get(2)?)),
+            )
+            .optional()?)
+    }
+
+    /// The mirrored schedule, with its terms and reservations.
+    pub fn schedule(&self) -> anyhow::Result<Schedule> {
+        let schedule = json!({
+            "Terms": self.load("terms", "name")?,
+            "Schedule": self.load("reservations", "reservation_id")?,
+        });
+
+        Ok(serde_json::from_value(schedule)?)
+    }
+
+    pub fn exams(&self) -> anyhow::Result<Vec<Exam>> {
+        self.load_as("exams", "exam_id")
+    }
+
+    pub fn groups(&self) -> anyhow::Result<Vec<Group>> {
+        self.load_as("course_groups", "course_id")
+    }
+
+    pub fn messages(&self) -> anyhow::Result<Vec<Message>> {
+        self.load_as("messages", "id")
+    }
+
+    pub fn notes(&self) -> anyhow::Result<Vec<Note>> {
+        self.load_as("notes", "id")
+    }
+}
+
+/// Takes the list under `key` out of a response, failing if the response doesn't have one.
+fn take_list(json: &mut Value, key: &str) -> anyhow::Result<Value> {
+    json.get_mut(key)
+        .map(Value::take)
+        .ok_or_else(|| parser::missing(&format!("{} list", key)))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        transport::{MemoryTransport, Method, Response},
+        Secret, Session,
+    };
+
+    fn message(id: u32, subject: &str) -> Value {
+        json!({ "Id": id, "Subject": subject, "TimeStamp": "2022-03-14 12:30" })
+    }
+
+    #[test]
+    fn upserts_by_id() {
+        let mut store = Store::in_memory().unwrap();
+
+        let messages = json!([message(1, "A"), message(2, "B")]);
+        let changes = store
+            .replace::<Message>("messages", "id", "Id", messages)
+            .unwrap();
+        assert_eq!(changes.inserted, 2);
+
+        let messages = json!([message(2, "B"), message(3, "C"), message(1, "Changed")]);
+        let changes = store
+            .replace::<Message>("messages", "id", "Id", messages)
+            .unwrap();
+        assert_eq!(
+            (changes.inserted, changes.updated, changes.deleted),
+            (1, 1, 0)
+        );
+
+        let messages = json!([message(3, "C")]);
+        let changes = store
+            .replace::<Message>("messages", "id", "Id", messages)
+            .unwrap();
+        assert_eq!(
+            (changes.inserted, changes.updated, changes.deleted),
+            (0, 0, 2)
+        );
+
+        let messages = store.messages().unwrap();
+        assert_eq!(messages.len(), 1);
+        assert_eq!(messages[0].subject, "C");
+    }
+
+    #[test]
+    fn rejects_unreadable_items() {
+        let mut store = Store::in_memory().unwrap();
+
+        let messages = json!([{ "Id": 1 }]);
+        assert!(store
+            .replace::<Message>("messages", "id", "Id", messages)
+            .is_err());
+        assert!(store
+            .replace::<Message>("messages", "id", "Id", Value::Null)
+            .is_err());
+    }
+
+    #[test]
+    fn migrations_are_idempotent() {
+        let mut store = Store::in_memory().unwrap();
+        schema::migrate(&mut store.connection).unwrap();
+
+        let version: u32 = store
+            .connection
+            .query_row("PRAGMA user_version", [], |row| row.get(0))
+            .unwrap();
+        assert_eq!(version, schema::latest_version());
+    }
+
+    #[tokio::test]
+    async fn syncs_from_wilma() {
+        const BASE_URL: &str = "https://wilma.example.com/!0123456/";
+        let messages = include_str!("../compat/fixtures/13/messages.json");
+
+        let mut transport = MemoryTransport::new();
+
+        for (path, body) in [
+            (
+                "",
+                include_str!("../parser/fixtures/student_front_page.html"),
+            ),
+            (
+                "schedule/export/students/123456",
+                r#"{ "Terms": [], "Schedule": [
+                    { "Day": 1, "Class": "", "Color": null, "Start": "08:15", "End": "09:30",
+                      "Groups": [], "ScheduleID": 1, "ReservationID": 1 }
+                ] }"#,
+            ),
+            ("overview", r#"{ "Schedule": [], "Exams": [] }"#),
+            (
+                "attendance/notes",
+                r#"{ "Notes": [{ "Id": 1, "Date": "2022-03-14", "Type": "late" }] }"#,
+            ),
+            // One of the messages is deleted before the second sync.
+            ("messages/list", messages),
+            (
+                "messages/list",
+                r#"{ "Messages": [{ "Id": 2, "Subject": "Retkipäivä",
+                                 "TimeStamp": "2022-03-14 12:30", "Sender": "Opettaja Olli",
+                                 "Status": 1 }] }"#,
+            ),
+        ] {
+            let url = format!("{}{}", BASE_URL, path);
+            transport = transport.with_response(Method::Get, url, Response::new(200, body));
+        }
+
+        let session = Session {
+            base_url: BASE_URL.into(),
+            id: Secret::new("session"),
+        };
+        let client = Client::resume_with(transport, session);
+        let mut store = Store::in_memory().unwrap();
+
+        let report = store.sync(&client).await.unwrap();
+        assert!(report.schedule_synced);
+        assert_eq!(report.reservations.inserted, 1);
+        assert_eq!(report.messages.inserted, 2);
+        assert_eq!(report.notes.inserted, 1);
+        assert_eq!(store.user().unwrap().unwrap().name(), "Meikäläinen Matti");
+
+        // The schedule is still fresh, so only the rest is fetched again.
+        let report = store.sync(&client).await.unwrap();
+        assert!(!report.schedule_synced);
+        assert_eq!(
+            report.messages,
+            Changes {
+                deleted: 1,
+                ..Default::default()
+            }
+        );
+        assert_eq!(report.notes, Changes::default());
+
+        assert_eq!(store.schedule().unwrap().reservations().len(), 1);
+        assert_eq!(store.messages().unwrap()[0].id, 2);
+    }
+}
@@ -0,0 +1,75 @@
+use rusqlite::Connection;
+
+/// The schema migrations. The schema version is stored in SQLite's `user_version`, and
+/// `MIGRATIONS[n]` upgrades the database from version `n` to `n + 1`. Never edit a migration that
+/// has been released; add a new one instead.
+pub const MIGRATIONS: &[&str] = &["
+    CREATE TABLE users (
+        user_id TEXT PRIMARY KEY,
+        name TEXT NOT NULL,
+        school TEXT NOT NULL,
+        formkey TEXT NOT NULL
+    );
+
+    CREATE TABLE terms (
+        name TEXT PRIMARY KEY,
+        data TEXT NOT NULL
+    );
+
+    CREATE TABLE reservations (
+        reservation_id INTEGER PRIMARY KEY,
+        data TEXT NOT NULL
+    );
+
+    CREATE TABLE exams (
+        exam_id INTEGER PRIMARY KEY,
+        data TEXT NOT NULL
+    );
+
+    CREATE TABLE course_groups (
+        course_id INTEGER PRIMARY KEY,
+        data TEXT NOT NULL
+    );
+
+    CREATE TABLE messages (
+        id INTEGER PRIMARY KEY,
+        data TEXT NOT NULL
+    );
+
+    CREATE TABLE notes (
+        id INTEGER PRIMARY KEY,
+        data TEXT NOT NULL
+    );
+
+    CREATE TABLE sync_state (
+        resource TEXT PRIMARY KEY,
+        synced_at INTEGER NOT NULL
+    );
+"];
+
+/// The schema version this version of the crate uses.
+pub fn latest_version() -> u32 {
+    MIGRATIONS.len() as u32
+}
+
+/// Brings the database up to the latest schema version.
+pub fn migrate(connection: &mut Connection) -> anyhow::Result<()> {
+    let version: u32 = connection.query_row("PRAGMA user_version", [], |row| row.get(0))?;
+
+    if version > latest_version() {
+        anyhow::bail!(
+            "The database has schema version {}, but the newest known version is {}.",
+            version,
+            latest_version()
+        );
+    }
+
+    for (index, migration) in MIGRATIONS.iter().enumerate().skip(version as usize) {
+        let transaction = connection.transaction()?;
+        transaction.execute_batch(migration)?;
+        transaction.pragma_update(None, "user_version", index as u32 + 1)?;
+        transaction.commit()?;
+    }
+
+    Ok(())
+}
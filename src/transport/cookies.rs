@@ -0,0 +1,116 @@
+use super::{Request, Response};
+use url::Url;
+
+/// Cookies collected during a multi-step login, keyed by host. Only used for the login steps;
+/// a logged in client sends just its session cookie.
+#[derive(Debug, Clone, Default)]
+pub struct CookieJar {
+    cookies: Vec<(String, String, String)>,
+}
+
+impl CookieJar {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Stores the cookies a response to `url` set, replacing older ones with the same name.
+    pub fn store(&mut self, url: &str, response: &Response) {
+        let host = host(url);
+
+        for (name, value) in response.cookies() {
+            self.cookies.retain(|(h, n, _)| !(h == &host && n == name));
+            self.cookies.push((host.clone(), name.into(), value.into()));
+        }
+    }
+
+    /// The value of a cookie set by a host.
+    pub fn get(&self, url: &str, name: &str) -> Option<&str> {
+        let host = host(url);
+
+        self.cookies
+            .iter()
+            .find(|(h, n, _)| h == &host && n == name)
+            .map(|(_, _, value)| value.as_str())
+    }
+
+    /// Adds a `Cookie` header with the cookies of the request's host, if there are any.
+    pub fn apply(&self, request: Request) -> Request {
+        let host = host(&request.url);
+
+        let cookies: Vec<String> = self
+            .cookies
+            .iter()
+            .filter(|(h, _, _)| h == &host)
+            .map(|(_, name, value)| format!("{}={}", name, value))
+            .collect();
+
+        match cookies.is_empty() {
+            true => request,
+            false => request.header("Cookie", cookies.join("; ")),
+        }
+    }
+}
+
+fn host(url: &str) -> String {
+    Url::parse(url)
+        .ok()
+        .and_then(|url| url.host_str().map(String::from))
+        .unwrap_or_default()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn keeps_cookies_per_host() {
+        let mut jar = CookieJar::new();
+
+        jar.store(
+            "https://wilma.example.com/login",
+            &Response::new(303, "")
+                .with_header("Set-Cookie", "Wilma2MfaID=pending; path=/; HttpOnly")
+                .with_header("Set-Cookie", "lang=fi"),
+        );
+        jar.store(
+            "https://idp.example.com/authorize",
+            &Response::new(200, "").with_header("Set-Cookie", "state=s"),
+        );
+
+        assert_eq!(
+            jar.get("https://wilma.example.com/mfa", "Wilma2MfaID"),
+            Some("pending")
+        );
+        assert_eq!(jar.get("https://wilma.example.com/", "state"), None);
+
+        let request = jar.apply(Request::get("https://wilma.example.com/mfa"));
+        assert_eq!(
+            request.headers,
+            [("Cookie".into(), "Wilma2MfaID=pending; lang=fi".into())]
+        );
+
+        let request = jar.apply(Request::get("https://other.example.com/"));
+        assert!(request.headers.is_empty());
+    }
+
+    #[test]
+    fn replaces_cookies_with_the_same_name() {
+        let mut jar = CookieJar::new();
+        let url = "https://wilma.example.com/login";
+
+        jar.store(
+            url,
+            &Response::new(200, "").with_header("Set-Cookie", "a=1"),
+        );
+        jar.store(
+            url,
+            &Response::new(200, "").with_header("Set-Cookie", "a=2"),
+        );
+
+        assert_eq!(jar.get(url, "a"), Some("2"));
+        assert_eq!(
+            jar.apply(Request::get(url)).headers,
+            [("Cookie".into(), "a=2".into())]
+        );
+    }
+}
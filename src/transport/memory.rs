@@ -0,0 +1,69 @@
+use super::{Method, Request, Response, Transport};
+use async_trait::async_trait;
+use std::collections::{HashMap, VecDeque};
+use std::sync::Mutex;
+
+/// A transport serving canned responses, for testing code built on the client without a
+/// network.
+///
+/// Responses are matched by method and URL (including the query string). If several responses
+/// are given for the same request, they are served in order and the last one is repeated.
+/// Requests without a response get a 404.
+#[derive(Default)]
+pub struct MemoryTransport {
+    responses: Mutex<HashMap<(Method, String), VecDeque<Response>>>,
+    requests: Mutex<Vec<Request>>,
+}
+
+impl MemoryTransport {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Adds a response for the given method and URL.
+    pub fn with_response<T: Into<String>>(
+        self,
+        method: Method,
+        url: T,
+        response: Response,
+    ) -> Self {
+        self.responses
+            .lock()
+            .unwrap_or_else(|e| e.into_inner())
+            .entry((method, url.into()))
+            .or_default()
+            .push_back(response);
+
+        self
+    }
+
+    /// Every request sent so far, in order.
+    pub fn requests(&self) -> Vec<Request> {
+        self.requests
+            .lock()
+            .unwrap_or_else(|e| e.into_inner())
+            .clone()
+    }
+}
+
+#[async_trait]
+impl Transport for MemoryTransport {
+    async fn send(&self, request: Request) -> anyhow::Result<Response> {
+        let key = (request.method, request.url.clone());
+
+        self.requests
+            .lock()
+            .unwrap_or_else(|e| e.into_inner())
+            .push(request);
+
+        let mut responses = self.responses.lock().unwrap_or_else(|e| e.into_inner());
+
+        let response = match responses.get_mut(&key) {
+            Some(queue) if queue.len() > 1 => queue.pop_front(),
+            Some(queue) => queue.front().cloned(),
+            None => None,
+        };
+
+        Ok(response.unwrap_or_else(|| Response::new(404, "")))
+    }
+}
@@ -0,0 +1,230 @@
+//! The HTTP layer of the client.
+//!
+//! `Client` is generic over a `Transport`, which sends a request and returns the status, the
+//! headers and the body of the response. Redirects are never followed, and cookies are handled
+//! by the client itself, so transports can be as simple as possible.
+
+use async_trait::async_trait;
+use std::error::Error as StdError;
+use std::fmt;
+use std::sync::Arc;
+
+pub mod cookies;
+pub use cookies::CookieJar;
+
+pub mod memory;
+pub use memory::MemoryTransport;
+
+#[cfg(feature = "reqwest")]
+pub mod reqwest;
+#[cfg(feature = "reqwest")]
+pub use self::reqwest::ReqwestTransport;
+
+/// The transport used when none is given, `ReqwestTransport`.
+#[cfg(feature = "reqwest")]
+pub type DefaultTransport = ReqwestTransport;
+
+/// Without the `reqwest` feature there is no default transport, and one has to be given with
+/// the `_with` constructors, like `Client::login_with`.
+#[cfg(not(feature = "reqwest"))]
+pub type DefaultTransport = NoTransport;
+
+/// A transport that can't be created, standing in for the default transport when the `reqwest`
+/// feature is disabled.
+#[cfg(not(feature = "reqwest"))]
+#[derive(Clone, Copy, Debug)]
+pub enum NoTransport {}
+
+/// An HTTP method. Wilma only needs these two.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum Method {
+    Get,
+    Post,
+}
+
+impl Method {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Method::Get => "GET",
+            Method::Post => "POST",
+        }
+    }
+}
+
+/// An HTTP request.
+#[derive(Clone, Debug)]
+pub struct Request {
+    pub method: Method,
+    pub url: String,
+    pub headers: Vec<(String, String)>,
+    pub body: Vec<u8>,
+}
+
+impl Request {
+    pub fn get<T: Into<String>>(url: T) -> Self {
+        Self {
+            method: Method::Get,
+            url: url.into(),
+            headers: Vec::new(),
+            body: Vec::new(),
+        }
+    }
+
+    pub fn post<T: Into<String>, B: Into<Vec<u8>>>(url: T, body: B) -> Self {
+        Self {
+            method: Method::Post,
+            url: url.into(),
+            headers: Vec::new(),
+            body: body.into(),
+        }
+    }
+
+    /// A POST request with an `application/x-www-form-urlencoded` body.
+    pub fn post_form<T: Into<String>>(url: T, form: &[(&str, &str)]) -> Self {
+        let body = url::form_urlencoded::Serializer::new(String::new())
+            .extend_pairs(form)
+            .finish();
+
+        Self::post(url, body).header("Content-Type", "application/x-www-form-urlencoded")
+    }
+
+    pub fn header<T: Into<String>, U: Into<String>>(mut self, name: T, value: U) -> Self {
+        self.headers.push((name.into(), value.into()));
+        self
+    }
+}
+
+/// An HTTP response.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Response {
+    pub status: u16,
+    pub headers: Vec<(String, String)>,
+    pub body: Vec<u8>,
+}
+
+impl Response {
+    pub fn new<T: Into<Vec<u8>>>(status: u16, body: T) -> Self {
+        Self {
+            status,
+            headers: Vec::new(),
+            body: body.into(),
+        }
+    }
+
+    pub fn with_header<T: Into<String>, U: Into<String>>(mut self, name: T, value: U) -> Self {
+        self.headers.push((name.into(), value.into()));
+        self
+    }
+
+    pub fn is_success(&self) -> bool {
+        (200..300).contains(&self.status)
+    }
+
+    /// The first value of a header. Header names are case-insensitive.
+    pub fn header(&self, name: &str) -> Option<&str> {
+        self.headers
+            .iter()
+            .find(|(n, _)| n.eq_ignore_ascii_case(name))
+            .map(|(_, v)| v.as_str())
+    }
+
+    /// Every value of a header, for headers like `Set-Cookie` that can appear multiple times.
+    pub fn header_values<'a>(&'a self, name: &'a str) -> impl Iterator<Item = &'a str> + 'a {
+        self.headers
+            .iter()
+            .filter(move |(n, _)| n.eq_ignore_ascii_case(name))
+            .map(|(_, v)| v.as_str())
+    }
+
+    /// Every cookie set by the response, as name-value pairs. Cookie attributes are ignored.
+    pub fn cookies(&self) -> impl Iterator<Item = (&str, &str)> {
+        self.header_values("Set-Cookie").filter_map(|header| {
+            let (name, value) = header.split(';').next()?.split_once('=')?;
+            Some((name.trim(), value.trim()))
+        })
+    }
+
+    /// The value of a cookie set by the response.
+    pub fn cookie(&self, name: &str) -> Option<&str> {
+        self.cookies()
+            .find(|(cookie, _)| *cookie == name)
+            .map(|(_, value)| value)
+    }
+
+    /// The body as text. Invalid UTF-8 is replaced rather than rejected.
+    pub fn text(&self) -> String {
+        String::from_utf8_lossy(&self.body).into_owned()
+    }
+}
+
+/// Something that can send HTTP requests.
+#[async_trait]
+pub trait Transport: Send + Sync {
+    /// Sends a request. Errors that are worth retrying, like timeouts, should be returned as a
+    /// retryable `TransportError`; other errors are never retried.
+    async fn send(&self, request: Request) -> anyhow::Result<Response>;
+}
+
+/// An error from a transport, telling whether sending the request again might succeed.
+#[derive(Debug)]
+pub struct TransportError {
+    retryable: bool,
+    source: anyhow::Error,
+}
+
+impl TransportError {
+    /// A temporary failure, like a timeout or a refused connection.
+    pub fn retryable<E: Into<anyhow::Error>>(source: E) -> Self {
+        Self {
+            retryable: true,
+            source: source.into(),
+        }
+    }
+
+    /// A failure that would happen again, like an invalid URL.
+    pub fn permanent<E: Into<anyhow::Error>>(source: E) -> Self {
+        Self {
+            retryable: false,
+            source: source.into(),
+        }
+    }
+
+    pub fn is_retryable(&self) -> bool {
+        self.retryable
+    }
+
+    /// Whether an error returned by a transport is a retryable `TransportError`.
+    pub fn is_retryable_error(error: &anyhow::Error) -> bool {
+        error
+            .downcast_ref::<TransportError>()
+            .is_some_and(|e| e.retryable)
+    }
+}
+
+impl fmt::Display for TransportError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}", self.source)
+    }
+}
+
+impl StdError for TransportError {
+    fn source(&self) -> Option<&(dyn StdError + 'static)> {
+        self.source.source()
+    }
+}
+
+#[cfg(not(feature = "reqwest"))]
+#[async_trait]
+impl Transport for NoTransport {
+    async fn send(&self, _: Request) -> anyhow::Result<Response> {
+        match *self {}
+    }
+}
+
+/// Lets several clients share one transport, for example a `MemoryTransport` in tests.
+#[async_trait]
+impl<T: Transport + ?Sized> Transport for Arc<T> {
+    async fn send(&self, request: Request) -> anyhow::Result<Response> {
+        (**self).send(request).await
+    }
+}
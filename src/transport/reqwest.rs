@@ -0,0 +1,76 @@
+use super::{Method, Request, Response, Transport, TransportError};
+use async_trait::async_trait;
+use reqwest::redirect::Policy;
+
+/// The default transport, backed by `reqwest`. Note that `reqwest` needs a tokio reactor.
+#[derive(Clone)]
+pub struct ReqwestTransport {
+    http: reqwest::Client,
+}
+
+impl ReqwestTransport {
+    pub fn new() -> anyhow::Result<Self> {
+        let http = reqwest::Client::builder()
+            .redirect(Policy::none())
+            .build()?;
+
+        Ok(Self { http })
+    }
+
+    /// Uses an already configured `reqwest` client. It shouldn't follow redirects or store
+    /// cookies, since the client handles those itself.
+    pub fn from_client(http: reqwest::Client) -> Self {
+        Self { http }
+    }
+}
+
+#[async_trait]
+impl Transport for ReqwestTransport {
+    async fn send(&self, request: Request) -> anyhow::Result<Response> {
+        let mut builder = match request.method {
+            Method::Get => self.http.get(&request.url),
+            Method::Post => self.http.post(&request.url),
+        };
+
+        for (name, value) in request.headers {
+            builder = builder.header(name, value);
+        }
+
+        if !request.body.is_empty() {
+            builder = builder.body(request.body);
+        }
+
+        let response = builder.send().await.map_err(TransportError::from)?;
+
+        let status = response.status().as_u16();
+        let headers = response
+            .headers()
+            .iter()
+            .map(|(name, value)| {
+                let value = String::from_utf8_lossy(value.as_bytes()).into_owned();
+                (name.to_string(), value)
+            })
+            .collect();
+        let body = response
+            .bytes()
+            .await
+            .map_err(TransportError::from)?
+            .to_vec();
+
+        Ok(Response {
+            status,
+            headers,
+            body,
+        })
+    }
+}
+
+impl From<reqwest::Error> for TransportError {
+    /// Timeouts and failed connections are retryable, other errors aren't.
+    fn from(error: reqwest::Error) -> Self {
+        match error.is_timeout() || error.is_connect() {
+            true => Self::retryable(error),
+            false => Self::permanent(error),
+        }
+    }
+}
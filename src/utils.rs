@@ -1,15 +1,41 @@
-use crate::wilma::user::UserType;
+use crate::{redact::redact, wilma::user::UserType};
+use std::time::Instant;
+use url::Url;
 
 // todo
 pub fn verify_url<T: AsRef<str>>(data: &T) -> String {
     format!("https://{}", data.as_ref())
 }
 
-/// A quick GET helper method to clean up code.
-pub async fn get<T: AsRef<str>>(url: T) -> anyhow::Result<String> {
-    let response_text = reqwest::get(url.as_ref()).await?.text().await?;
+/// Records a finished HTTP request as a tracing event. Only the path of the URL is recorded, and
+/// both the path and the body (which is only recorded at the trace level) are redacted.
+pub(crate) fn record_request(
+    method: &str,
+    url: &str,
+    status: u16,
+    started: Instant,
+    body: &str,
+    cached: bool,
+) {
+    let path = match Url::parse(url) {
+        Ok(url) => match url.query() {
+            Some(query) => format!("{}?{}", url.path(), query),
+            None => url.path().to_string(),
+        },
+        Err(_) => url.to_string(),
+    };
 
-    Ok(response_text)
+    tracing::debug!(
+        method,
+        path = %redact(&path),
+        status,
+        latency_ms = started.elapsed().as_millis() as u64,
+        bytes = body.len(),
+        cached,
+        "HTTP request"
+    );
+
+    tracing::trace!(body = %redact(body), "HTTP response body");
 }
 
 /// A utility for parsing formkeys. Returns a tuple that has a structure of (user_type, user_id,
@@ -0,0 +1,391 @@
+//! An encrypted file for storing credentials and sessions, for unattended syncing.
+//!
+//! The encryption key is derived from a passphrase with Argon2id, and the accounts are encrypted
+//! with XChaCha20-Poly1305, so a wrong passphrase or a tampered file is detected. Nothing is
+//! stored in plain text except the key derivation parameters.
+
+use crate::{accounts::AccountKey, Error as WilmaError, OwnedCredentials, Secret, Session};
+use argon2::{Algorithm, Argon2, Params, Version};
+use chacha20poly1305::{
+    aead::{Aead, KeyInit, Payload},
+    XChaCha20Poly1305, XNonce,
+};
+use rand::RngCore;
+use serde::{Deserialize, Serialize};
+use std::fs;
+use std::path::{Path, PathBuf};
+use zeroize::{Zeroize, ZeroizeOnDrop, Zeroizing};
+
+/// The version of the vault file format.
+pub const VERSION: u32 = 1;
+
+const SALT_LENGTH: usize = 16;
+const NONCE_LENGTH: usize = 24;
+
+/// Argon2id parameters, stored in the file so they can be raised later.
+#[derive(Debug, Clone, Copy, Serialize, Deserialize)]
+struct Kdf {
+    memory: u32,
+    iterations: u32,
+    parallelism: u32,
+}
+
+impl Kdf {
+    fn new() -> Self {
+        // Keep the tests fast; the default parameters take a while in debug builds.
+        if cfg!(test) {
+            return Self {
+                memory: 64,
+                iterations: 1,
+                parallelism: 1,
+            };
+        }
+
+        Self {
+            memory: Params::DEFAULT_M_COST,
+            iterations: Params::DEFAULT_T_COST,
+            parallelism: Params::DEFAULT_P_COST,
+        }
+    }
+
+    fn derive_key(&self, passphrase: &str, salt: &[u8]) -> anyhow::Result<Zeroizing<[u8; 32]>> {
+        let params = Params::new(self.memory, self.iterations, self.parallelism, Some(32))
+            .map_err(|e| anyhow::anyhow!("Invalid key derivation parameters: {}", e))?;
+
+        let mut key = Zeroizing::new([0; 32]);
+        Argon2::new(Algorithm::Argon2id, Version::V0x13, params)
+            .hash_password_into(passphrase.as_bytes(), salt, &mut *key)
+            .map_err(|e| anyhow::anyhow!("Couldn't derive the vault key: {}", e))?;
+
+        Ok(key)
+    }
+}
+
+/// The file on disk. Everything but the key derivation parameters is encrypted.
+#[derive(Serialize, Deserialize)]
+struct VaultFile {
+    version: u32,
+    kdf: Kdf,
+    salt: String,
+    nonce: String,
+    ciphertext: String,
+}
+
+/// An account as it's encrypted.
+#[derive(Default, Serialize, Deserialize, Zeroize, ZeroizeOnDrop)]
+struct StoredAccount {
+    server: String,
+    username: String,
+    role: Option<String>,
+    password: String,
+    base_url: Option<String>,
+    session: Option<String>,
+}
+
+struct Entry {
+    key: AccountKey,
+    credentials: OwnedCredentials,
+    session: Option<Session>,
+}
+
+/// Credentials and sessions of several accounts, encrypted with a passphrase.
+///
+/// Changes are only written to disk by `Vault::save`.
+pub struct Vault {
+    path: PathBuf,
+    kdf: Kdf,
+    salt: [u8; SALT_LENGTH],
+    key: Zeroizing<[u8; 32]>,
+    entries: Vec<Entry>,
+}
+
+impl Vault {
+    /// Opens the vault at the given path, or creates an empty one if the file doesn't exist.
+    /// Fails with `Error::InvalidPassphrase` if the passphrase is wrong.
+    pub fn open<P: AsRef<Path>>(path: P, passphrase: &str) -> anyhow::Result<Self> {
+        let path = path.as_ref().to_path_buf();
+
+        if !path.exists() {
+            let kdf = Kdf::new();
+            let salt = random();
+
+            return Ok(Self {
+                key: kdf.derive_key(passphrase, &salt)?,
+                path,
+                kdf,
+                salt,
+                entries: Vec::new(),
+            });
+        }
+
+        let file: VaultFile = serde_json::from_slice(&fs::read(&path)?)?;
+
+        if file.version != VERSION {
+            anyhow::bail!("Unsupported vault version {}.", file.version);
+        }
+
+        let salt: [u8; SALT_LENGTH] = decode(&file.salt)?;
+        let nonce: [u8; NONCE_LENGTH] = decode(&file.nonce)?;
+        let key = file.kdf.derive_key(passphrase, &salt)?;
+
+        let plaintext = XChaCha20Poly1305::new(key.as_ref().into())
+            .decrypt(
+                XNonce::from_slice(&nonce),
+                Payload {
+                    msg: &hex::decode(&file.ciphertext)?,
+                    aad: &associated_data(&file.kdf, &salt),
+                },
+            )
+            .map(Zeroizing::new)
+            .map_err(|_| WilmaError::InvalidPassphrase)?;
+
+        let stored: Vec<StoredAccount> = serde_json::from_slice(&plaintext)?;
+
+        Ok(Self {
+            path,
+            kdf: file.kdf,
+            salt,
+            key,
+            entries: stored.iter().map(Entry::from).collect(),
+        })
+    }
+
+    /// Encrypts the vault and writes it to disk, replacing the file atomically.
+    pub fn save(&self) -> anyhow::Result<()> {
+        let stored: Vec<StoredAccount> = self.entries.iter().map(StoredAccount::from).collect();
+        let plaintext = Zeroizing::new(serde_json::to_vec(&stored)?);
+
+        let nonce: [u8; NONCE_LENGTH] = random();
+        let ciphertext = XChaCha20Poly1305::new(self.key.as_ref().into())
+            .encrypt(
+                XNonce::from_slice(&nonce),
+                Payload {
+                    msg: &plaintext,
+                    aad: &associated_data(&self.kdf, &self.salt),
+                },
+            )
+            .map_err(|_| anyhow::anyhow!("Couldn't encrypt the vault."))?;
+
+        let file = VaultFile {
+            version: VERSION,
+            kdf: self.kdf,
+            salt: hex::encode(self.salt),
+            nonce: hex::encode(nonce),
+            ciphertext: hex::encode(ciphertext),
+        };
+
+        let temporary = self.path.with_extension("tmp");
+        write_private(&temporary, &serde_json::to_vec(&file)?)?;
+        fs::rename(&temporary, &self.path)?;
+
+        Ok(())
+    }
+
+    /// Re-encrypts the vault with a new passphrase on the next save.
+    pub fn change_passphrase(&mut self, passphrase: &str) -> anyhow::Result<()> {
+        self.kdf = Kdf::new();
+        self.salt = random();
+        self.key = self.kdf.derive_key(passphrase, &self.salt)?;
+
+        Ok(())
+    }
+
+    /// Adds an account, replacing the credentials and forgetting the session of an existing one
+    /// with the same key.
+    pub fn add(&mut self, credentials: OwnedCredentials, role: Option<&str>) -> AccountKey {
+        let key = AccountKey {
+            server: credentials.server.clone(),
+            username: credentials.username.clone(),
+            role: role.map(String::from),
+        };
+
+        self.remove(&key);
+        self.entries.push(Entry {
+            key: key.clone(),
+            credentials,
+            session: None,
+        });
+
+        key
+    }
+
+    /// Removes an account. Returns whether the account existed.
+    pub fn remove(&mut self, key: &AccountKey) -> bool {
+        let count = self.entries.len();
+        self.entries.retain(|e| &e.key != key);
+
+        self.entries.len() != count
+    }
+
+    /// The keys of every account, in the order they were added.
+    pub fn keys(&self) -> Vec<&AccountKey> {
+        self.entries.iter().map(|e| &e.key).collect()
+    }
+
+    pub fn credentials(&self, key: &AccountKey) -> Option<&OwnedCredentials> {
+        self.entry(key).map(|e| &e.credentials)
+    }
+
+    /// The stored session of an account. See `Client::resume`.
+    pub fn session(&self, key: &AccountKey) -> Option<&Session> {
+        self.entry(key).and_then(|e| e.session.as_ref())
+    }
+
+    /// Stores the session of an account, or forgets it with `None`.
+    pub fn set_session(
+        &mut self,
+        key: &AccountKey,
+        session: Option<Session>,
+    ) -> anyhow::Result<()> {
+        match self.entries.iter_mut().find(|e| &e.key == key) {
+            Some(entry) => {
+                entry.session = session;
+                Ok(())
+            }
+            None => anyhow::bail!("No account {} in the vault.", key),
+        }
+    }
+
+    fn entry(&self, key: &AccountKey) -> Option<&Entry> {
+        self.entries.iter().find(|e| &e.key == key)
+    }
+}
+
+impl From<&StoredAccount> for Entry {
+    fn from(stored: &StoredAccount) -> Self {
+        let session = match (&stored.base_url, &stored.session) {
+            (Some(base_url), Some(id)) => Some(Session {
+                base_url: base_url.clone(),
+                id: Secret::new(id.as_str()),
+            }),
+            _ => None,
+        };
+
+        Self {
+            key: AccountKey {
+                server: stored.server.clone(),
+                username: stored.username.clone(),
+                role: stored.role.clone(),
+            },
+            credentials: OwnedCredentials::new(
+                stored.username.as_str(),
+                stored.password.as_str(),
+                stored.server.as_str(),
+            ),
+            session,
+        }
+    }
+}
+
+impl From<&Entry> for StoredAccount {
+    fn from(entry: &Entry) -> Self {
+        Self {
+            server: entry.key.server.clone(),
+            username: entry.key.username.clone(),
+            role: entry.key.role.clone(),
+            password: entry.credentials.password.expose().into(),
+            base_url: entry.session.as_ref().map(|s| s.base_url.clone()),
+            session: entry.session.as_ref().map(|s| s.id.expose().into()),
+        }
+    }
+}
+
+/// Binds the ciphertext to the key derivation parameters, so they can't be swapped.
+fn associated_data(kdf: &Kdf, salt: &[u8]) -> Vec<u8> {
+    format!(
+        "openwilma-vault:{}:{}:{}:{}:{}",
+        VERSION,
+        kdf.memory,
+        kdf.iterations,
+        kdf.parallelism,
+        hex::encode(salt)
+    )
+    .into_bytes()
+}
+
+fn random<const N: usize>() -> [u8; N] {
+    let mut bytes = [0; N];
+    rand::thread_rng().fill_bytes(&mut bytes);
+    bytes
+}
+
+fn decode<const N: usize>(data: &str) -> anyhow::Result<[u8; N]> {
+    hex::decode(data)?
+        .try_into()
+        .map_err(|_| anyhow::anyhow!("The vault file is corrupted."))
+}
+
+/// Writes a file only the current user can read.
+fn write_private(path: &Path, data: &[u8]) -> anyhow::Result<()> {
+    use std::io::Write;
+
+    let mut options = fs::OpenOptions::new();
+    options.write(true).create(true).truncate(true);
+
+    #[cfg(unix)]
+    std::os::unix::fs::OpenOptionsExt::mode(&mut options, 0o600);
+
+    options.open(path)?.write_all(data)?;
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn path(name: &str) -> PathBuf {
+        std::env::temp_dir().join(format!("openwilma-{}-{}.vault", name, std::process::id()))
+    }
+
+    #[test]
+    fn round_trips() {
+        let path = path("round-trip");
+
+        let mut vault = Vault::open(&path, "correct horse").unwrap();
+        let key = vault.add(
+            OwnedCredentials::new("user", "hunter2", "wilma.example.com"),
+            Some("!01"),
+        );
+        let session = Session {
+            base_url: "https://wilma.example.com/!01/".into(),
+            id: Secret::new("sid"),
+        };
+        vault.set_session(&key, Some(session.clone())).unwrap();
+        vault.save().unwrap();
+
+        let contents = fs::read_to_string(&path).unwrap();
+        assert!(!contents.contains("hunter2"));
+        assert!(!contents.contains("user"));
+
+        let vault = Vault::open(&path, "correct horse").unwrap();
+        assert_eq!(vault.keys(), vec![&key]);
+        assert_eq!(
+            vault.credentials(&key).unwrap().password.expose(),
+            "hunter2"
+        );
+        assert_eq!(vault.session(&key), Some(&session));
+
+        fs::remove_file(&path).unwrap();
+    }
+
+    #[test]
+    fn rejects_wrong_passphrase() {
+        let path = path("wrong-passphrase");
+
+        let mut vault = Vault::open(&path, "correct horse").unwrap();
+        vault.add(
+            OwnedCredentials::new("user", "hunter2", "wilma.example.com"),
+            None,
+        );
+        vault.save().unwrap();
+
+        let error = Vault::open(&path, "battery staple").err().unwrap();
+        assert!(matches!(
+            error.downcast_ref::<WilmaError>(),
+            Some(WilmaError::InvalidPassphrase)
+        ));
+
+        fs::remove_file(&path).unwrap();
+    }
+}
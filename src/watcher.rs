@@ -0,0 +1,452 @@
+use crate::{
+    runtime::{self, Timer},
+    transport::{DefaultTransport, Transport},
+    wilma::{
+        schedule::{Time, Weekday},
+        Exam, Grade, Message, News, Note, Overview, Reservation, Schedule,
+    },
+    Client,
+};
+use futures::stream::{self, Stream};
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, BTreeSet, VecDeque};
+use std::fs;
+use std::path::PathBuf;
+use std::sync::Arc;
+use std::time::Duration;
+
+/// A change detected between two snapshots.
+#[derive(Debug, Clone)]
+pub enum Event {
+    /// A new message arrived in the inbox.
+    NewMessage(Message),
+
+    /// A news article was published.
+    NewsPublished(News),
+
+    /// A teacher gave a new note.
+    NoteAdded(Note),
+
+    /// A course was graded for the first time.
+    GradeAdded(Grade),
+
+    /// The grade of a course changed.
+    GradeChanged { grade: Grade, previous: String },
+
+    /// A new exam appeared.
+    ExamScheduled(Exam),
+
+    /// An exam was graded.
+    ExamGraded(Exam),
+
+    /// A new reservation appeared in the schedule.
+    LessonAdded(Reservation),
+
+    /// A reservation disappeared from the schedule. The lesson is none if the state was saved by
+    /// a version that didn't remember lessons.
+    LessonCancelled {
+        reservation_id: u32,
+        lesson: Option<Lesson>,
+    },
+
+    /// A reservation was moved into a different room.
+    RoomChanged {
+        reservation: Reservation,
+        previous_rooms: Vec<u32>,
+    },
+}
+
+/// What the watcher remembers of a reservation, for reporting it after it's gone.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Lesson {
+    /// The captions of the groups, for example "MAA1".
+    pub course: String,
+    pub weekday: Weekday,
+    pub start: Time,
+    pub end: Time,
+}
+
+impl From<&Reservation> for Lesson {
+    fn from(reservation: &Reservation) -> Self {
+        let groups: Vec<&str> = reservation
+            .groups
+            .iter()
+            .map(|g| g.caption().as_str())
+            .collect();
+
+        Self {
+            course: groups.join(", "),
+            weekday: reservation.weekday,
+            start: reservation.start,
+            end: reservation.end,
+        }
+    }
+}
+
+/// Everything the watcher looks at, fetched at one point in time.
+#[derive(Debug, Clone)]
+pub struct Snapshot {
+    pub overview: Overview,
+    pub schedule: Schedule,
+    pub messages: Vec<Message>,
+    pub news: Vec<News>,
+    pub notes: Vec<Note>,
+    pub grades: Vec<Grade>,
+}
+
+impl Snapshot {
+    pub async fn take<T: Transport>(client: &Client<T>) -> anyhow::Result<Self> {
+        Ok(Self {
+            overview: client.get_overview().await?,
+            schedule: client.get_user_schedule().await?,
+            messages: client.get_messages().await?,
+            news: client.get_news().await?,
+            notes: client.get_notes().await?,
+            grades: client.get_grades().await?,
+        })
+    }
+}
+
+fn room_ids(reservation: &Reservation) -> Vec<u32> {
+    let mut rooms: Vec<u32> = reservation
+        .groups
+        .iter()
+        .flat_map(|g| g.rooms().iter().map(|r| r.id()))
+        .collect();
+
+    rooms.sort_unstable();
+    rooms.dedup();
+    rooms
+}
+
+/// The parts of a snapshot needed for computing diffs. This is what gets persisted between
+/// restarts, so only IDs and the values that are compared are stored.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct State {
+    messages: BTreeSet<u32>,
+    news: BTreeSet<u32>,
+    notes: BTreeSet<u32>,
+    grades: BTreeMap<u32, String>,
+    exams: BTreeMap<u32, Option<String>>,
+    reservations: BTreeMap<u32, Vec<u32>>,
+    #[serde(default)]
+    lessons: BTreeMap<u32, Lesson>,
+}
+
+impl From<&Snapshot> for State {
+    fn from(snapshot: &Snapshot) -> Self {
+        Self {
+            messages: snapshot.messages.iter().map(|m| m.id).collect(),
+            news: snapshot.news.iter().map(|n| n.id).collect(),
+            notes: snapshot.notes.iter().map(|n| n.id).collect(),
+            grades: snapshot
+                .grades
+                .iter()
+                .map(|g| (g.course_id, g.grade.clone()))
+                .collect(),
+            exams: snapshot
+                .overview
+                .exams()
+                .iter()
+                .map(|e| (e.exam_id, e.grade.clone()))
+                .collect(),
+            reservations: snapshot
+                .schedule
+                .reservations()
+                .iter()
+                .map(|r| (r.reservation_id, room_ids(r)))
+                .collect(),
+            lessons: snapshot
+                .schedule
+                .reservations()
+                .iter()
+                .map(|r| (r.reservation_id, Lesson::from(r)))
+                .collect(),
+        }
+    }
+}
+
+impl State {
+    /// Computes the events that happened between this state and the given snapshot.
+    pub fn diff(&self, snapshot: &Snapshot) -> Vec<Event> {
+        let mut events = Vec::new();
+
+        for message in snapshot.messages.iter() {
+            if !self.messages.contains(&message.id) {
+                events.push(Event::NewMessage(message.clone()));
+            }
+        }
+
+        for news in snapshot.news.iter() {
+            if !self.news.contains(&news.id) {
+                events.push(Event::NewsPublished(news.clone()));
+            }
+        }
+
+        for note in snapshot.notes.iter() {
+            if !self.notes.contains(&note.id) {
+                events.push(Event::NoteAdded(note.clone()));
+            }
+        }
+
+        for grade in snapshot.grades.iter() {
+            match self.grades.get(&grade.course_id) {
+                None => events.push(Event::GradeAdded(grade.clone())),
+                Some(previous) if *previous != grade.grade => events.push(Event::GradeChanged {
+                    grade: grade.clone(),
+                    previous: previous.clone(),
+                }),
+                Some(_) => {}
+            }
+        }
+
+        for exam in snapshot.overview.exams().iter() {
+            match self.exams.get(&exam.exam_id) {
+                None => events.push(Event::ExamScheduled(exam.clone())),
+                Some(None) if exam.grade.is_some() => events.push(Event::ExamGraded(exam.clone())),
+                Some(_) => {}
+            }
+        }
+
+        let reservations = snapshot.schedule.reservations();
+
+        for reservation in reservations.iter() {
+            let rooms = room_ids(reservation);
+
+            match self.reservations.get(&reservation.reservation_id) {
+                None => events.push(Event::LessonAdded(reservation.clone())),
+                Some(previous_rooms) if *previous_rooms != rooms => {
+                    events.push(Event::RoomChanged {
+                        reservation: reservation.clone(),
+                        previous_rooms: previous_rooms.clone(),
+                    })
+                }
+                Some(_) => {}
+            }
+        }
+
+        for reservation_id in self.reservations.keys() {
+            if !reservations
+                .iter()
+                .any(|r| r.reservation_id == *reservation_id)
+            {
+                events.push(Event::LessonCancelled {
+                    reservation_id: *reservation_id,
+                    lesson: self.lessons.get(reservation_id).cloned(),
+                });
+            }
+        }
+
+        events
+    }
+}
+
+/// Periodically snapshots a client's data and reports what changed.
+///
+/// The first snapshot only records a baseline and doesn't produce any events. If a state file is
+/// set, the state is persisted after every snapshot so restarts don't report old changes again.
+pub struct Watcher<T: Transport = DefaultTransport> {
+    client: Client<T>,
+    interval: Duration,
+    state: Option<State>,
+    state_file: Option<PathBuf>,
+    timer: Arc<dyn Timer>,
+}
+
+impl<T: Transport> Watcher<T> {
+    pub fn new(client: Client<T>, interval: Duration) -> Self {
+        Self {
+            client,
+            interval,
+            state: None,
+            state_file: None,
+            timer: runtime::default_timer(),
+        }
+    }
+
+    /// Sets the timer used for waiting between snapshots.
+    pub fn with_timer(mut self, timer: Arc<dyn Timer>) -> Self {
+        self.timer = timer;
+        self
+    }
+
+    /// Loads the state from the given file (if it exists) and persists it there after every
+    /// snapshot.
+    pub fn with_state_file<P: Into<PathBuf>>(mut self, path: P) -> anyhow::Result<Self> {
+        let path = path.into();
+
+        if path.exists() {
+            self.state = Some(serde_json::from_str(&fs::read_to_string(&path)?)?);
+        }
+
+        self.state_file = Some(path);
+        Ok(self)
+    }
+
+    /// Takes a new snapshot and returns the changes since the previous one.
+    pub async fn poll(&mut self) -> anyhow::Result<Vec<Event>> {
+        let snapshot = Snapshot::take(&self.client).await?;
+
+        let events = match &self.state {
+            Some(state) => state.diff(&snapshot),
+            None => Vec::new(),
+        };
+
+        let state = State::from(&snapshot);
+
+        if let Some(path) = &self.state_file {
+            // Write to a temporary file first so a crash can't leave a half-written state behind.
+            let temporary = path.with_extension("tmp");
+            fs::write(&temporary, serde_json::to_string(&state)?)?;
+            fs::rename(&temporary, path)?;
+        }
+
+        self.state = Some(state);
+        Ok(events)
+    }
+
+    /// Turns the watcher into a stream of events. A snapshot is taken immediately, and then once
+    /// every interval. Errors are yielded as items and don't end the stream.
+    pub fn into_stream(self) -> impl Stream<Item = anyhow::Result<Event>> {
+        stream::unfold(
+            (self, VecDeque::new(), true),
+            |(mut watcher, mut pending, mut first)| async move {
+                loop {
+                    if let Some(event) = pending.pop_front() {
+                        return Some((Ok(event), (watcher, pending, first)));
+                    }
+
+                    if !first {
+                        watcher.timer.sleep(watcher.interval).await;
+                    }
+                    first = false;
+
+                    match watcher.poll().await {
+                        Ok(events) => pending.extend(events),
+                        Err(e) => return Some((Err(e), (watcher, pending, first))),
+                    }
+                }
+            },
+        )
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        transport::{MemoryTransport, Method, Response},
+        Error as WilmaError, Secret, Session,
+    };
+
+    fn snapshot(room: u32, grade: &str, message: u32) -> Snapshot {
+        let reservation = format!(
+            r#"{{ "Day": 1, "Class": "", "Color": null, "Start": "08:15", "End": "09:30",
+                  "ScheduleID": 1, "ReservationID": 7, "Groups": [{{
+                      "Caption": "MAA1", "Class": "", "CourseId": 3, "FullCaption": "",
+                      "Id": 4, "ShortCaption": "", "Teachers": [],
+                      "Rooms": [{{ "Caption": "A{room}", "Id": {room}, "LongCaption": "",
+                                   "ScheduleVisible": true }}]
+                  }}] }}"#
+        );
+
+        Snapshot {
+            overview: serde_json::from_str(r#"{ "Schedule": [], "Exams": [] }"#).unwrap(),
+            schedule: serde_json::from_str(&format!(
+                r#"{{ "Terms": [], "Schedule": [{}] }}"#,
+                reservation
+            ))
+            .unwrap(),
+            messages: vec![serde_json::from_str(&format!(
+                r#"{{ "Id": {}, "Subject": "Hei", "TimeStamp": "2022-03-14 12:30" }}"#,
+                message
+            ))
+            .unwrap()],
+            news: Vec::new(),
+            notes: Vec::new(),
+            grades: vec![serde_json::from_str(&format!(
+                r#"{{ "CourseId": 3, "CourseName": "MAA1", "Grade": "{}" }}"#,
+                grade
+            ))
+            .unwrap()],
+        }
+    }
+
+    #[test]
+    fn unchanged_snapshot_has_no_events() {
+        let state = State::from(&snapshot(1, "8", 1));
+        assert!(state.diff(&snapshot(1, "8", 1)).is_empty());
+    }
+
+    #[test]
+    fn detects_changes() {
+        let state = State::from(&snapshot(1, "8", 1));
+
+        // Persisting and restoring the state must not lose anything.
+        let state: State = serde_json::from_str(&serde_json::to_string(&state).unwrap()).unwrap();
+
+        let events = state.diff(&snapshot(2, "9", 2));
+
+        assert!(matches!(events[0], Event::NewMessage(ref m) if m.id == 2));
+        assert!(matches!(events[1], Event::GradeChanged { ref previous, .. } if previous == "8"));
+        assert!(
+            matches!(events[2], Event::RoomChanged { ref previous_rooms, .. } if previous_rooms == &vec![1])
+        );
+        assert_eq!(events.len(), 3);
+    }
+
+    #[tokio::test]
+    async fn missing_lists_fail_the_poll() {
+        const BASE_URL: &str = "https://wilma.example.com/!0123456/";
+        let messages = include_str!("compat/fixtures/13/messages.json");
+
+        let mut transport = MemoryTransport::new()
+            .with_response(
+                Method::Get,
+                BASE_URL,
+                Response::new(200, include_str!("parser/fixtures/student_front_page.html")),
+            )
+            .with_response(
+                Method::Get,
+                format!("{}schedule/export/students/123456", BASE_URL),
+                Response::new(200, r#"{ "Terms": [], "Schedule": [] }"#),
+            )
+            .with_response(
+                Method::Get,
+                format!("{}overview", BASE_URL),
+                Response::new(200, r#"{ "Schedule": [], "Exams": [] }"#),
+            );
+
+        for (path, body) in [
+            ("news/list", r#"{ "News": [] }"#),
+            ("attendance/notes", r#"{ "Notes": [] }"#),
+            ("grades/list", r#"{ "Grades": [] }"#),
+        ] {
+            let url = format!("{}{}", BASE_URL, path);
+            transport = transport.with_response(Method::Get, url, Response::new(200, body));
+        }
+
+        // The second poll gets a response without the list.
+        let url = format!("{}messages/list", BASE_URL);
+        for body in [messages, r#"{ "Status": 200 }"#, messages] {
+            transport = transport.with_response(Method::Get, &url, Response::new(200, body));
+        }
+
+        let session = Session {
+            base_url: BASE_URL.into(),
+            id: Secret::new("session"),
+        };
+        let mut watcher = Watcher::new(Client::resume_with(transport, session), Duration::ZERO);
+
+        assert!(watcher.poll().await.unwrap().is_empty());
+
+        let error = watcher.poll().await.unwrap_err();
+        assert!(matches!(
+            error.downcast_ref::<WilmaError>(),
+            Some(WilmaError::UnexpectedPage)
+        ));
+
+        // The messages weren't forgotten, so they aren't reported as new.
+        assert!(watcher.poll().await.unwrap().is_empty());
+    }
+}
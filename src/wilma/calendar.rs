@@ -0,0 +1,103 @@
+use chrono::NaiveDate;
+use serde::{Deserialize, Serialize};
+
+/// The type of a calendar event.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(from = "String")]
+pub enum EventType {
+    /// A school event, for example a sports day or a theme day.
+    SchoolEvent,
+
+    /// A day (or a range of days) when the school is closed.
+    Holiday,
+
+    /// An exam week. Regular lessons usually don't take place during these.
+    ExamWeek,
+
+    /// The start or the end of a period.
+    PeriodBoundary,
+
+    /// Unknown
+    Unknown,
+}
+
+impl<T: AsRef<str>> From<T> for EventType {
+    fn from(data: T) -> Self {
+        use EventType::*;
+
+        match data.as_ref() {
+            "event" | "schoolevent" => SchoolEvent,
+            "holiday" | "vacation" => Holiday,
+            "examweek" => ExamWeek,
+            "period" | "periodstart" | "periodend" => PeriodBoundary,
+            _ => Unknown,
+        }
+    }
+}
+
+/// A single entry in the school calendar.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all(deserialize = "PascalCase"))]
+pub struct Event {
+    /// The ID of the event.
+    pub id: u32,
+
+    /// The type of the event.
+    #[serde(rename(deserialize = "Type"))]
+    pub event_type: EventType,
+
+    /// The title of the event.
+    pub caption: String,
+
+    /// A longer description of the event. Will be none if the school didn't write one.
+    #[serde(default)]
+    pub description: Option<String>,
+
+    /// The first day of the event.
+    pub start_date: NaiveDate,
+
+    /// The last day of the event (inclusive). Same as `start_date` for one-day events.
+    pub end_date: NaiveDate,
+}
+
+impl Event {
+    /// Whether the event takes place on the given date.
+    pub fn contains(&self, date: NaiveDate) -> bool {
+        self.start_date <= date && date <= self.end_date
+    }
+
+    /// Whether the school is closed during the event.
+    pub fn is_holiday(&self) -> bool {
+        self.event_type == EventType::Holiday
+    }
+}
+
+/// The school calendar, consisting of events, holidays, exam weeks and period boundaries.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(rename_all(deserialize = "PascalCase"))]
+pub struct Calendar {
+    events: Vec<Event>,
+}
+
+impl Calendar {
+    pub fn new(events: Vec<Event>) -> Self {
+        Self { events }
+    }
+
+    /// All of the events in the calendar.
+    pub fn events(&self) -> &Vec<Event> {
+        &self.events
+    }
+
+    /// The events that take place on the given date.
+    pub fn events_on(&self, date: NaiveDate) -> Vec<&Event> {
+        self.events.iter().filter(|e| e.contains(date)).collect()
+    }
+
+    /// Whether the school is closed on the given date.
+    pub fn is_holiday(&self, date: NaiveDate) -> bool {
+        self.events
+            .iter()
+            .any(|e| e.is_holiday() && e.contains(date))
+    }
+}
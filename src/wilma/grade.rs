@@ -0,0 +1,19 @@
+use serde::{Deserialize, Serialize};
+
+/// A course grade.
+#[derive(Serialize, Deserialize, Clone, Debug)]
+#[serde(rename_all(deserialize = "PascalCase"))]
+pub struct Grade {
+    /// The ID of the course the grade is from.
+    pub course_id: u32,
+
+    /// The name of the course the grade is from.
+    pub course_name: String,
+
+    /// The grade itself, for example "9", "S" or "H".
+    pub grade: String,
+
+    /// The date the grade was given on.
+    #[serde(default)]
+    pub date: Option<String>,
+}
@@ -22,3 +22,46 @@ pub struct Group {
 
     committed: bool,
 }
+
+impl Group {
+    pub fn id(&self) -> u32 {
+        self.id
+    }
+
+    /// The ID of the course the group belongs to.
+    pub fn course_id(&self) -> u32 {
+        self.course_id
+    }
+
+    pub fn course_name(&self) -> &String {
+        &self.course_name
+    }
+
+    /// The course code, for example "MAA1".
+    pub fn code(&self) -> &String {
+        &self.code
+    }
+
+    pub fn name(&self) -> &String {
+        &self.name
+    }
+
+    pub fn caption(&self) -> &String {
+        &self.caption
+    }
+
+    /// The date the group starts.
+    pub fn start_date(&self) -> &String {
+        &self.start_date
+    }
+
+    /// The date the group ends.
+    pub fn end_date(&self) -> &String {
+        &self.end_date
+    }
+
+    /// Whether the user's enrollment to the group is confirmed.
+    pub fn committed(&self) -> bool {
+        self.committed
+    }
+}
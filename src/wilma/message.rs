@@ -0,0 +1,31 @@
+use serde::{Deserialize, Serialize};
+
+/// A message from the user's inbox.
+#[derive(Serialize, Deserialize, Clone, Debug)]
+#[serde(rename_all(deserialize = "PascalCase"))]
+pub struct Message {
+    /// The ID of the message.
+    pub id: u32,
+
+    /// The subject of the message.
+    pub subject: String,
+
+    /// The time when the message was sent, for example "2022-03-14 12:30".
+    #[serde(rename(deserialize = "TimeStamp"))]
+    pub timestamp: String,
+
+    /// The name of the sender. Will be none for messages sent by the user.
+    #[serde(default)]
+    pub sender: Option<String>,
+
+    /// The read status of the message. Wilma uses `1` for unread messages.
+    #[serde(default)]
+    pub status: u32,
+}
+
+impl Message {
+    /// Whether the user hasn't read the message yet.
+    pub fn is_unread(&self) -> bool {
+        self.status == 1
+    }
+}
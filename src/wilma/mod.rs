@@ -4,7 +4,7 @@ pub mod user;
 pub use user::User;
 
 pub mod schedule;
-pub use schedule::Schedule;
+pub use schedule::{Reservation, Schedule};
 
 pub mod overview;
 pub use overview::Overview;
@@ -15,6 +15,21 @@ pub use exam::Exam;
 pub mod group;
 pub use group::Group;
 
+pub mod calendar;
+pub use calendar::Calendar;
+
+pub mod message;
+pub use message::Message;
+
+pub mod news;
+pub use news::News;
+
+pub mod note;
+pub use note::Note;
+
+pub mod grade;
+pub use grade::Grade;
+
 #[derive(Serialize, Deserialize, Clone, Debug)]
 #[serde(rename_all(deserialize = "PascalCase"))]
 pub struct IndexResponse {
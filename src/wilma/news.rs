@@ -0,0 +1,24 @@
+use serde::{Deserialize, Serialize};
+
+/// A news article published on the Wilma server.
+#[derive(Serialize, Deserialize, Clone, Debug)]
+#[serde(rename_all(deserialize = "PascalCase"))]
+pub struct News {
+    /// The ID of the article.
+    pub id: u32,
+
+    /// The title of the article.
+    pub title: String,
+
+    /// A short description of the article. Will be none if the article doesn't have one.
+    #[serde(default)]
+    pub description: Option<String>,
+
+    /// The date the article was published on.
+    #[serde(rename(deserialize = "PublishDate"))]
+    pub published: String,
+
+    /// The name of the author.
+    #[serde(default)]
+    pub sender: Option<String>,
+}
@@ -0,0 +1,70 @@
+use serde::{Deserialize, Serialize};
+
+/// A note type enum. Check each field to check out what they represent.
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, Eq)]
+#[serde(from = "String")]
+pub enum NoteType {
+    /// Unexcused absence
+    Absence,
+
+    /// Absence excused by a guardian or the school
+    ExcusedAbsence,
+
+    /// Late to a lesson
+    Late,
+
+    /// Forgotten homework or equipment
+    Forgotten,
+
+    /// Positive feedback
+    Praise,
+
+    /// Disturbing the lesson or other misbehaviour
+    Warning,
+
+    /// Unknown
+    Unknown,
+}
+
+impl<T: AsRef<str>> From<T> for NoteType {
+    fn from(data: T) -> Self {
+        use NoteType::*;
+
+        match data.as_ref() {
+            "absence" => Absence,
+            "excused" => ExcusedAbsence,
+            "late" => Late,
+            "forgotten" => Forgotten,
+            "praise" => Praise,
+            "warning" => Warning,
+            _ => Unknown,
+        }
+    }
+}
+
+/// A note (attendance marking) given by a teacher.
+#[derive(Serialize, Deserialize, Clone, Debug)]
+#[serde(rename_all(deserialize = "PascalCase"))]
+pub struct Note {
+    /// The ID of the note.
+    pub id: u32,
+
+    /// The type of the note.
+    #[serde(rename(deserialize = "Type"))]
+    pub note_type: NoteType,
+
+    /// The date the note was given on.
+    pub date: String,
+
+    /// The course the note was given in. Will be none for notes not tied to a lesson.
+    #[serde(default)]
+    pub course: Option<String>,
+
+    /// The name of the teacher who gave the note.
+    #[serde(default)]
+    pub teacher: Option<String>,
+
+    /// An explanation written by the teacher or a guardian.
+    #[serde(default)]
+    pub reason: Option<String>,
+}
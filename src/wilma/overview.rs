@@ -1,4 +1,4 @@
-use crate::wilma::{schedule::Reservation, Exam};
+use crate::wilma::{schedule::Reservation, Exam, Group};
 use serde::{Deserialize, Serialize};
 
 #[derive(Serialize, Deserialize, Clone, Debug)]
@@ -6,4 +6,23 @@ use serde::{Deserialize, Serialize};
 pub struct Overview {
     schedule: Vec<Reservation>,
     exams: Vec<Exam>,
+    #[serde(default)]
+    groups: Vec<Group>,
+}
+
+impl Overview {
+    /// Today's reservations.
+    pub fn schedule(&self) -> &Vec<Reservation> {
+        &self.schedule
+    }
+
+    /// Upcoming exams.
+    pub fn exams(&self) -> &Vec<Exam> {
+        &self.exams
+    }
+
+    /// The course groups the user is in.
+    pub fn groups(&self) -> &Vec<Group> {
+        &self.groups
+    }
 }
@@ -0,0 +1,226 @@
+//! Differences between the schedule of a week and the regular timetable: cancellations,
+//! substitute teachers, room changes and moved or extra lessons.
+//!
+//! Unstable, behind the `unstable-week-schedule` feature: the week endpoint and the `Cancelled`,
+//! `Substitutes` and `OriginalRooms` fields aren't confirmed from captured responses yet, so they
+//! may change.
+
+use super::{Reservation, Room, Schedule, Teacher};
+
+/// A lesson that deviates from the regular timetable. See `Schedule::changes_from`.
+#[derive(Debug, Clone)]
+pub enum LessonChange<'a> {
+    /// A regular lesson that is cancelled or missing from the week. Always the reservation of
+    /// the regular timetable.
+    Cancelled(&'a Reservation),
+
+    /// A lesson that isn't in the regular timetable.
+    Added(&'a Reservation),
+
+    /// A regular lesson that takes place on another day or at another time.
+    Moved {
+        from: &'a Reservation,
+        to: &'a Reservation,
+    },
+
+    /// A lesson taught by substitute teachers.
+    Substitute {
+        lesson: &'a Reservation,
+        teachers: Vec<&'a Teacher>,
+    },
+
+    /// A lesson in other rooms than usual.
+    RoomChanged {
+        lesson: &'a Reservation,
+        from: Vec<&'a Room>,
+        to: Vec<&'a Room>,
+    },
+}
+
+impl<'a> LessonChange<'a> {
+    /// The lesson of the week, or the regular one if the lesson is cancelled.
+    pub fn lesson(&self) -> &'a Reservation {
+        use LessonChange::*;
+
+        match self {
+            Cancelled(lesson) | Added(lesson) => lesson,
+            Moved { to, .. } => to,
+            Substitute { lesson, .. } | RoomChanged { lesson, .. } => lesson,
+        }
+    }
+}
+
+impl Schedule {
+    /// Compares the schedule of a week (see `Client::get_user_week_schedule`) to the regular
+    /// timetable. Lessons are matched by their reservation ID, and the changes are ordered by
+    /// the time of the lesson.
+    pub fn changes_from<'a>(&'a self, regular: &'a Schedule) -> Vec<LessonChange<'a>> {
+        let mut changes = Vec::new();
+
+        for lesson in &regular.reservations {
+            let week = self
+                .reservations
+                .iter()
+                .find(|r| r.reservation_id == lesson.reservation_id);
+
+            match week {
+                None => changes.push(LessonChange::Cancelled(lesson)),
+                Some(week) => changes.extend(compare(lesson, week)),
+            }
+        }
+
+        for lesson in &self.reservations {
+            let regular = regular
+                .reservations
+                .iter()
+                .any(|r| r.reservation_id == lesson.reservation_id);
+
+            if !regular && !lesson.cancelled {
+                changes.push(LessonChange::Added(lesson));
+            }
+        }
+
+        changes.sort_by_key(|c| (c.lesson().weekday, c.lesson().span()));
+        changes
+    }
+}
+
+/// The changes of a regular lesson in a week.
+fn compare<'a>(regular: &'a Reservation, week: &'a Reservation) -> Vec<LessonChange<'a>> {
+    if week.cancelled {
+        return vec![LessonChange::Cancelled(regular)];
+    }
+
+    let mut changes = Vec::new();
+
+    if week.weekday != regular.weekday || week.span() != regular.span() {
+        changes.push(LessonChange::Moved {
+            from: regular,
+            to: week,
+        });
+    }
+
+    let teachers: Vec<&Teacher> = week.groups.iter().flat_map(|g| g.substitutes()).collect();
+
+    if !teachers.is_empty() {
+        changes.push(LessonChange::Substitute {
+            lesson: week,
+            teachers,
+        });
+    }
+
+    let rooms: Vec<&Room> = week.groups.iter().flat_map(|g| g.rooms()).collect();
+    let original: Vec<&Room> = week
+        .groups
+        .iter()
+        .flat_map(|g| g.original_rooms())
+        .collect();
+
+    // Wilma doesn't always say where the lesson was moved from, so compare to the regular rooms.
+    let from = match original.is_empty() {
+        true => regular.groups.iter().flat_map(|g| g.rooms()).collect(),
+        false => original,
+    };
+
+    if room_ids(&from) != room_ids(&rooms) {
+        changes.push(LessonChange::RoomChanged {
+            lesson: week,
+            from,
+            to: rooms,
+        });
+    }
+
+    changes
+}
+
+fn room_ids(rooms: &[&Room]) -> Vec<u32> {
+    let mut ids: Vec<u32> = rooms.iter().map(|r| r.id()).collect();
+    ids.sort_unstable();
+    ids.dedup();
+    ids
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::Value;
+
+    /// The regular timetable, and a week of it with a substitute teacher on Monday, a cancelled
+    /// lesson on Wednesday and a room change on Thursday.
+    const REGULAR: &str = include_str!("fixtures/regular.json");
+    const WEEK: &str = include_str!("fixtures/week.json");
+
+    /// Parses a schedule fixture after editing its reservations.
+    fn schedule(fixture: &str, edit: impl FnOnce(&mut Vec<Value>)) -> Schedule {
+        let mut json: Value = serde_json::from_str(fixture).unwrap();
+
+        if let Some(Value::Array(reservations)) = json.get_mut("Schedule") {
+            edit(reservations);
+        }
+
+        serde_json::from_value(json).unwrap()
+    }
+
+    #[test]
+    fn finds_changes_of_the_week() {
+        let regular = schedule(REGULAR, |_| {});
+        let week = schedule(WEEK, |_| {});
+
+        let changes = week.changes_from(&regular);
+        assert_eq!(changes.len(), 3);
+
+        assert!(matches!(
+            &changes[0],
+            LessonChange::Substitute { teachers, .. } if teachers[0].id == 404
+        ));
+        assert!(matches!(
+            &changes[1],
+            LessonChange::Cancelled(r) if r.reservation_id == 12 && !r.cancelled
+        ));
+        assert!(matches!(
+            &changes[2],
+            LessonChange::RoomChanged { from, to, .. } if from[0].id() == 303 && to[0].id() == 304
+        ));
+    }
+
+    #[test]
+    fn finds_moved_missing_and_extra_lessons() {
+        let regular = schedule(REGULAR, |_| {});
+        let week = schedule(REGULAR, |reservations| {
+            // Monday's lesson is on Tuesday, Wednesday's is missing and there's one on Friday.
+            reservations[0]["Day"] = 2.into();
+
+            let mut extra = reservations.remove(1);
+            extra["ReservationID"] = 14.into();
+            extra["Day"] = 5.into();
+            reservations.push(extra);
+        });
+
+        let changes = week.changes_from(&regular);
+        assert_eq!(changes.len(), 3);
+
+        assert!(matches!(
+            &changes[0],
+            LessonChange::Moved { from, to } if from.weekday < to.weekday
+        ));
+        assert!(matches!(&changes[1], LessonChange::Cancelled(r) if r.reservation_id == 12));
+        assert!(matches!(&changes[2], LessonChange::Added(r) if r.reservation_id == 14));
+    }
+
+    #[test]
+    fn compares_to_the_regular_rooms_without_original_rooms() {
+        let regular = schedule(REGULAR, |_| {});
+        let week = schedule(WEEK, |reservations| {
+            reservations[2]["Groups"][0]
+                .as_object_mut()
+                .unwrap()
+                .remove("OriginalRooms");
+        });
+
+        let changes = week.changes_from(&regular);
+        assert!(matches!(
+            &changes[2],
+            LessonChange::RoomChanged { from, .. } if from[0].id() == 303
+        ));
+    }
+}
@@ -1,9 +1,17 @@
+use crate::wilma::calendar::{Calendar, Event};
+use chrono::{Datelike, NaiveDate};
 use serde::{Deserialize, Serialize};
 
+#[cfg(feature = "unstable-week-schedule")]
+pub mod changes;
+pub mod now;
 pub mod reservation;
 pub mod term;
 
-pub use reservation::Reservation;
+#[cfg(feature = "unstable-week-schedule")]
+pub use changes::LessonChange;
+pub use now::NowAndNext;
+pub use reservation::{Reservation, Room, Teacher, Time, TimeSpan, Weekday};
 pub use term::Term;
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -14,3 +22,150 @@ pub struct Schedule {
     #[serde(rename(deserialize = "Schedule"))]
     reservations: Vec<Reservation>,
 }
+
+/// A reservation placed on a specific date, along with the calendar events of that day.
+#[derive(Debug, Clone)]
+pub struct Lesson<'a> {
+    /// The date the lesson takes place on.
+    pub date: NaiveDate,
+
+    /// The weekly reservation the lesson is an occurrence of.
+    pub reservation: &'a Reservation,
+
+    /// Special events (school events, exam weeks, period boundaries) of the day.
+    pub events: Vec<&'a Event>,
+}
+
+impl Schedule {
+    /// The terms the schedule covers.
+    pub fn terms(&self) -> &Vec<Term> {
+        &self.terms
+    }
+
+    /// The weekly reservations of the schedule.
+    pub fn reservations(&self) -> &Vec<Reservation> {
+        &self.reservations
+    }
+
+    /// The rooms used in the schedule whose own schedules are visible to the user. Each room is
+    /// only listed once.
+    pub fn rooms(&self) -> Vec<&Room> {
+        let mut rooms: Vec<&Room> = Vec::new();
+
+        for room in self
+            .reservations
+            .iter()
+            .flat_map(|r| r.groups.iter())
+            .flat_map(|g| g.rooms().iter())
+            .filter(|r| r.schedule_visible())
+        {
+            if !rooms.iter().any(|r| r.id() == room.id()) {
+                rooms.push(room);
+            }
+        }
+
+        rooms
+    }
+
+    /// The teachers in the schedule whose own schedules are visible to the user. Each teacher is
+    /// only listed once.
+    pub fn teachers(&self) -> Vec<&Teacher> {
+        let mut teachers: Vec<&Teacher> = Vec::new();
+
+        for teacher in self
+            .reservations
+            .iter()
+            .flat_map(|r| r.groups.iter())
+            .flat_map(|g| g.teachers().iter())
+            .filter(|t| t.schedule_visible)
+        {
+            if !teachers.iter().any(|t| t.id == teacher.id) {
+                teachers.push(teacher);
+            }
+        }
+
+        teachers
+    }
+
+    /// Expands the weekly reservations into lessons on actual dates between `start` and `end`
+    /// (inclusive). Days outside of the schedule's terms and holidays in the calendar are skipped.
+    pub fn expand<'a>(
+        &'a self,
+        start: NaiveDate,
+        end: NaiveDate,
+        calendar: &'a Calendar,
+    ) -> Vec<Lesson<'a>> {
+        let mut lessons = Vec::new();
+
+        for date in start.iter_days().take_while(|d| *d <= end) {
+            if calendar.is_holiday(date) || !self.terms.iter().any(|t| t.contains(date)) {
+                continue;
+            }
+
+            let weekday = Weekday::from(date.weekday());
+
+            for reservation in self.reservations.iter().filter(|r| r.weekday == weekday) {
+                lessons.push(Lesson {
+                    date,
+                    reservation,
+                    events: calendar.events_on(date),
+                });
+            }
+        }
+
+        lessons
+    }
+
+    /// Expands the weekly reservations over a whole term. See `Schedule::expand`.
+    pub fn expand_term<'a>(&'a self, term: &Term, calendar: &'a Calendar) -> Vec<Lesson<'a>> {
+        match (term.start_date(), term.end_date()) {
+            (Some(start), Some(end)) => self.expand(start, end, calendar),
+            _ => Vec::new(),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn expand_skips_holidays() {
+        let schedule: Schedule = serde_json::from_str(
+            r#"{
+                "Terms": [{ "Name": "Syksy", "StartDate": "2022-08-08", "EndDate": "2022-12-21" }],
+                "Schedule": [{
+                    "Day": 1, "Class": "1A", "Color": null, "Start": "08:15", "End": 570,
+                    "Groups": [], "ScheduleID": 1, "ReservationID": 2
+                }]
+            }"#,
+        )
+        .unwrap();
+
+        let calendar: Calendar = serde_json::from_str(
+            r#"{
+                "Events": [
+                    { "Id": 1, "Type": "holiday", "Caption": "Syysloma",
+                      "StartDate": "2022-10-17", "EndDate": "2022-10-21" },
+                    { "Id": 2, "Type": "event", "Caption": "Liikuntapäivä",
+                      "StartDate": "2022-10-24", "EndDate": "2022-10-24" }
+                ]
+            }"#,
+        )
+        .unwrap();
+
+        let start = NaiveDate::from_ymd_opt(2022, 10, 10).unwrap();
+        let end = NaiveDate::from_ymd_opt(2022, 10, 30).unwrap();
+        let lessons = schedule.expand(start, end, &calendar);
+
+        let dates: Vec<NaiveDate> = lessons.iter().map(|l| l.date).collect();
+        assert_eq!(
+            dates,
+            vec![
+                NaiveDate::from_ymd_opt(2022, 10, 10).unwrap(),
+                NaiveDate::from_ymd_opt(2022, 10, 24).unwrap(),
+            ]
+        );
+        assert_eq!(lessons[1].events[0].caption, "Liikuntapäivä");
+    }
+}
@@ -0,0 +1,170 @@
+//! "Now and next" queries, for school info screens.
+
+use super::{Reservation, Schedule, Time, Weekday};
+use crate::runtime::Clock;
+use chrono::{Datelike, Duration, NaiveDateTime};
+
+/// The lessons of a day around a moment. See `Schedule::now`.
+#[derive(Debug, Clone)]
+pub struct NowAndNext<'a> {
+    /// The time of the moment.
+    pub time: Time,
+
+    /// The lesson going on at the moment.
+    pub current: Option<&'a Reservation>,
+
+    /// The first lesson starting after the current one, or after the moment if there's none.
+    pub next: Option<&'a Reservation>,
+
+    /// The lessons of the day that haven't started yet, in order. Includes `next`.
+    pub remaining: Vec<&'a Reservation>,
+
+    /// When the last lesson of the day ends. None if there are no lessons on the day.
+    pub end_of_day: Option<Time>,
+
+    /// When the next break starts, after the current lesson and any lessons right after it.
+    /// None if there's no lesson going on.
+    pub next_break: Option<Time>,
+}
+
+impl NowAndNext<'_> {
+    /// The time until the next lesson starts.
+    pub fn until_next(&self) -> Option<Duration> {
+        self.next.map(|r| r.start - self.time)
+    }
+
+    /// The time until the next break starts.
+    pub fn until_break(&self) -> Option<Duration> {
+        self.next_break.map(|t| t - self.time)
+    }
+
+    /// Whether the lessons of the day are over, or there were none.
+    pub fn is_day_over(&self) -> bool {
+        self.end_of_day.is_none_or(|end| end <= self.time)
+    }
+}
+
+impl Schedule {
+    /// The ongoing and upcoming lessons at the time of the clock. Holidays and term boundaries
+    /// aren't taken into account; see `Schedule::expand` for those.
+    pub fn now(&self, clock: &dyn Clock) -> NowAndNext<'_> {
+        self.at(clock.now())
+    }
+
+    /// The ongoing and upcoming lessons at a moment. See `Schedule::now`.
+    pub fn at(&self, moment: NaiveDateTime) -> NowAndNext<'_> {
+        let time = Time::from(moment.time());
+        let weekday = Weekday::from(moment.weekday());
+
+        let mut lessons: Vec<&Reservation> = self
+            .reservations
+            .iter()
+            .filter(|r| r.weekday == weekday)
+            .collect();
+        lessons.sort_by_key(|r| r.span());
+
+        let current = lessons.iter().find(|r| r.span().contains(time)).copied();
+        let remaining: Vec<&Reservation> =
+            lessons.iter().filter(|r| r.start > time).copied().collect();
+
+        let next = match current {
+            Some(current) => remaining.iter().find(|r| r.start >= current.end),
+            None => remaining.first(),
+        }
+        .copied();
+
+        NowAndNext {
+            time,
+            current,
+            next,
+            end_of_day: lessons.iter().map(|r| r.end).max(),
+            next_break: current.map(|r| break_after(&lessons, r.end)),
+            remaining,
+        }
+    }
+}
+
+/// Follows back-to-back lessons from the end of a lesson until there's a gap.
+fn break_after(lessons: &[&Reservation], mut end: Time) -> Time {
+    while let Some(lesson) = lessons.iter().find(|r| r.start <= end && end < r.end) {
+        end = lesson.end;
+    }
+
+    end
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::runtime::FixedClock;
+    use chrono::NaiveDate;
+
+    fn schedule() -> Schedule {
+        serde_json::from_str(
+            r#"{
+                "Terms": [],
+                "Schedule": [
+                    { "Day": 1, "Class": "", "Color": null, "Start": "09:30", "End": "10:45",
+                      "Groups": [], "ScheduleID": 1, "ReservationID": 2 },
+                    { "Day": 1, "Class": "", "Color": null, "Start": "08:15", "End": "09:30",
+                      "Groups": [], "ScheduleID": 1, "ReservationID": 1 },
+                    { "Day": 1, "Class": "", "Color": null, "Start": "12:00", "End": "13:15",
+                      "Groups": [], "ScheduleID": 1, "ReservationID": 3 }
+                ]
+            }"#,
+        )
+        .unwrap()
+    }
+
+    /// A clock on Monday 2022-03-14 at the given time.
+    fn monday(time: &str) -> FixedClock {
+        let time = time.parse::<Time>().unwrap();
+        let date = NaiveDate::from_ymd_opt(2022, 3, 14).unwrap();
+
+        FixedClock(date.and_hms_opt(time.hours(), time.minutes(), 0).unwrap())
+    }
+
+    fn ids(reservations: &[&Reservation]) -> Vec<u32> {
+        reservations.iter().map(|r| r.reservation_id).collect()
+    }
+
+    #[test]
+    fn finds_the_current_and_next_lessons() {
+        let schedule = schedule();
+
+        let before = schedule.now(&monday("8:00"));
+        assert!(before.current.is_none());
+        assert_eq!(before.next.unwrap().reservation_id, 1);
+        assert_eq!(before.until_next(), Some(Duration::minutes(15)));
+        assert_eq!(ids(&before.remaining), [1, 2, 3]);
+
+        let during = schedule.now(&monday("9:00"));
+        assert_eq!(during.current.unwrap().reservation_id, 1);
+        assert_eq!(during.next.unwrap().reservation_id, 2);
+        assert_eq!(during.next_break, Some("10:45".parse().unwrap()));
+        assert_eq!(during.until_break(), Some(Duration::minutes(105)));
+        assert_eq!(during.end_of_day, Some("13:15".parse().unwrap()));
+
+        let lunch = schedule.now(&monday("11:00"));
+        assert!(lunch.current.is_none());
+        assert_eq!(lunch.next_break, None);
+        assert_eq!(ids(&lunch.remaining), [3]);
+        assert!(!lunch.is_day_over());
+    }
+
+    #[test]
+    fn ends_the_day() {
+        let schedule = schedule();
+
+        let after = schedule.now(&monday("13:15"));
+        assert!(after.current.is_none());
+        assert!(after.next.is_none());
+        assert!(after.is_day_over());
+
+        let tuesday = FixedClock(monday("9:00").0 + Duration::days(1));
+        let tuesday = schedule.now(&tuesday);
+        assert!(tuesday.current.is_none());
+        assert_eq!(tuesday.end_of_day, None);
+        assert!(tuesday.is_day_over());
+    }
+}
@@ -1,108 +1,262 @@
+use crate::locale::{Language, Localize};
+use chrono::{Duration, NaiveTime, Timelike};
 use serde::de;
-use serde::{Deserialize, Deserializer, Serialize};
+use serde::{Deserialize, Deserializer, Serialize, Serializer};
 use std::fmt;
+use std::ops::{Add, Sub};
+use std::str::FromStr;
 
-#[derive(Debug, Clone, Serialize, Deserialize)]
-#[serde(from = "u32")]
-/// Weekday enum. One-based.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash, Serialize, Deserialize)]
+#[serde(try_from = "u32", into = "u32")]
+/// Weekday enum. One-based, starting from Monday.
 pub enum Weekday {
     Monday,
     Tuesday,
     Wednesday,
     Thursday,
     Friday,
+    Saturday,
+    Sunday,
 }
 
 impl Weekday {
-    pub fn fmt(&self) -> String {
-        use Weekday::*;
+    /// Every weekday, starting from Monday.
+    pub const ALL: [Weekday; 7] = [
+        Weekday::Monday,
+        Weekday::Tuesday,
+        Weekday::Wednesday,
+        Weekday::Thursday,
+        Weekday::Friday,
+        Weekday::Saturday,
+        Weekday::Sunday,
+    ];
 
-        match self {
-            Monday => "Monday",
-            Tuesday => "Tuesday",
-            Wednesday => "Wednesday",
-            Thursday => "Thursday",
-            Friday => "Friday",
-        }
-        .to_string()
+    pub fn fmt(&self) -> String {
+        self.localize(Language::English).to_string()
     }
 
     pub fn fmt_finnish(&self) -> String {
-        use Weekday::*;
+        self.localize(Language::Finnish).to_string()
+    }
+
+    /// The one-based number of the weekday, as used by Wilma.
+    pub fn number(&self) -> u32 {
+        *self as u32 + 1
+    }
+}
 
-        match self {
-            Monday => "Maanantai",
-            Tuesday => "Tiistai",
-            Wednesday => "Keskiviikko",
-            Thursday => "Torstai",
-            Friday => "Perjantai",
+impl TryFrom<u32> for Weekday {
+    type Error = anyhow::Error;
+
+    fn try_from(data: u32) -> anyhow::Result<Self> {
+        match data {
+            1..=7 => Ok(Self::ALL[data as usize - 1]),
+            _ => anyhow::bail!("Invalid weekday number {} (expected 1-7).", data),
         }
-        .to_string()
     }
 }
 
-impl From<u32> for Weekday {
-    fn from(data: u32) -> Self {
+impl From<Weekday> for u32 {
+    fn from(weekday: Weekday) -> Self {
+        weekday.number()
+    }
+}
+
+impl From<chrono::Weekday> for Weekday {
+    fn from(weekday: chrono::Weekday) -> Self {
+        Self::ALL[weekday.num_days_from_monday() as usize]
+    }
+}
+
+impl From<Weekday> for chrono::Weekday {
+    fn from(weekday: Weekday) -> Self {
         use Weekday::*;
 
-        match data {
-            1 => Monday,
-            2 => Tuesday,
-            3 => Wednesday,
-            4 => Thursday,
-            5 => Friday,
-            _ => panic!("Tried to build weekday enum from an invalid number (expected 1-5)."),
+        match weekday {
+            Monday => chrono::Weekday::Mon,
+            Tuesday => chrono::Weekday::Tue,
+            Wednesday => chrono::Weekday::Wed,
+            Thursday => chrono::Weekday::Thu,
+            Friday => chrono::Weekday::Fri,
+            Saturday => chrono::Weekday::Sat,
+            Sunday => chrono::Weekday::Sun,
         }
     }
 }
 
-/// A time struct for creating abstractions around reservation start times.
-#[derive(Debug, Clone, Serialize, Deserialize)]
+const MINUTES_PER_DAY: u32 = 24 * 60;
+
+/// A time of day with minute precision, from 00:00 to 24:00.
+///
+/// Times can be parsed from every format Wilma uses: minutes since midnight, `13:50`, `13.50`
+/// and `13:50:00`. Arithmetic never panics; the operators saturate at the start and the end of
+/// the day, and `Time::checked_add` and `Time::checked_sub` return none instead.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, PartialOrd, Ord, Hash)]
 pub struct Time {
+    minutes: u16,
+}
+
+impl Time {
+    pub const MIDNIGHT: Time = Time { minutes: 0 };
+
+    /// 24:00, the end of a reservation that lasts until midnight.
+    pub const END_OF_DAY: Time = Time {
+        minutes: MINUTES_PER_DAY as u16,
+    };
+
+    /// Fails if the minutes aren't below 60 or the time is after 24:00.
+    pub fn new(hours: u32, minutes: u32) -> anyhow::Result<Self> {
+        if minutes >= 60 {
+            anyhow::bail!("Invalid time {}:{:02}.", hours, minutes);
+        }
+
+        hours
+            .checked_mul(60)
+            .and_then(|h| h.checked_add(minutes))
+            .ok_or_else(|| anyhow::anyhow!("Invalid time {}:{:02}.", hours, minutes))
+            .and_then(Self::from_minutes)
+    }
+
+    /// A time from the number of minutes since midnight. Fails if it's after 24:00.
+    pub fn from_minutes(minutes: u32) -> anyhow::Result<Self> {
+        if minutes > MINUTES_PER_DAY {
+            anyhow::bail!("Invalid time of {} minutes after midnight.", minutes);
+        }
+
+        Ok(Self {
+            minutes: minutes as u16,
+        })
+    }
+
     /// The hour the time is at (for example, the time 13:50 is at hour 13).
-    pub hours: u32,
+    pub fn hours(&self) -> u32 {
+        self.total_minutes() / 60
+    }
 
     /// The minute the time is at (for example, the time 13:50 is at minute 50).
-    pub minutes: u32,
-}
+    pub fn minutes(&self) -> u32 {
+        self.total_minutes() % 60
+    }
 
-impl Time {
-    /// Returns a formatted string. A time struct where `self.hours == 13` and `self.minutes == 50`
-    /// would return "13:50".
+    /// The number of minutes since midnight.
+    pub fn total_minutes(&self) -> u32 {
+        self.minutes as u32
+    }
+
+    /// Returns a formatted string. The time 13:50 would return "13:50", and 8:05 "8:05".
     pub fn fmt(&self) -> String {
-        let minutes_fmt = match self.minutes < 10 {
-            true => format!("0{}", self.minutes),
-            false => self.minutes.to_string(),
-        };
+        self.to_string()
+    }
+
+    /// The time after a duration, or none if it's outside of the day. Seconds are ignored.
+    pub fn checked_add(&self, duration: Duration) -> Option<Self> {
+        let minutes = (self.total_minutes() as i64).checked_add(duration.num_minutes())?;
+        Self::from_minutes(u32::try_from(minutes).ok()?).ok()
+    }
+
+    /// The time before a duration, or none if it's outside of the day. Seconds are ignored.
+    pub fn checked_sub(&self, duration: Duration) -> Option<Self> {
+        let minutes = (self.total_minutes() as i64).checked_sub(duration.num_minutes())?;
+        Self::from_minutes(u32::try_from(minutes).ok()?).ok()
+    }
+}
+
+impl Add<Duration> for Time {
+    type Output = Time;
+
+    fn add(self, duration: Duration) -> Time {
+        self.checked_add(duration)
+            .unwrap_or(match duration < Duration::zero() {
+                true => Time::MIDNIGHT,
+                false => Time::END_OF_DAY,
+            })
+    }
+}
+
+impl Sub<Duration> for Time {
+    type Output = Time;
 
-        format!("{}:{}", self.hours, minutes_fmt)
+    fn sub(self, duration: Duration) -> Time {
+        self.checked_sub(duration)
+            .unwrap_or(match duration < Duration::zero() {
+                true => Time::END_OF_DAY,
+                false => Time::MIDNIGHT,
+            })
     }
 }
 
-impl From<u32> for Time {
-    fn from(data: u32) -> Self {
-        let hours = (data as f32 / 60.0).floor();
-        let time_hours_and_minutes = data as f32 / 60.0;
+impl Sub for Time {
+    type Output = Duration;
 
-        let minutes = (time_hours_and_minutes - hours) * 60.0;
+    /// The duration between two times. Negative if `other` is later.
+    fn sub(self, other: Time) -> Duration {
+        Duration::minutes(self.total_minutes() as i64 - other.total_minutes() as i64)
+    }
+}
 
+impl From<NaiveTime> for Time {
+    /// Truncates the seconds.
+    fn from(time: NaiveTime) -> Self {
         Self {
-            hours: hours as u32,
-            minutes: minutes as u32,
+            minutes: (time.hour() * 60 + time.minute()) as u16,
         }
     }
 }
 
-impl From<&'_ str> for Time {
-    fn from(data: &'_ str) -> Self {
-        let (hours, minutes) = data.split_once(":").expect("Invalid time string provided.");
+impl TryFrom<u32> for Time {
+    type Error = anyhow::Error;
 
-        Self {
-            hours: hours.parse::<u32>().expect("Invalid time string provided."),
-            minutes: minutes
-                .parse::<u32>()
-                .expect("Invalid time string provided."),
+    /// See `Time::from_minutes`.
+    fn try_from(minutes: u32) -> anyhow::Result<Self> {
+        Self::from_minutes(minutes)
+    }
+}
+
+impl FromStr for Time {
+    type Err = anyhow::Error;
+
+    fn from_str(data: &str) -> anyhow::Result<Self> {
+        let data = data.trim();
+        let invalid = || anyhow::anyhow!("Invalid time {:?}.", data);
+        let number = |part: Option<&str>| {
+            part.filter(|p| !p.is_empty() && p.bytes().all(|b| b.is_ascii_digit()))
+                .and_then(|p| p.parse::<u32>().ok())
+                .ok_or_else(invalid)
+        };
+
+        // Minutes since midnight, sent as a string.
+        if !data.contains([':', '.']) {
+            return Self::from_minutes(number(Some(data))?);
+        }
+
+        let mut parts = data.split([':', '.']);
+        let hours = number(parts.next())?;
+        let minutes = number(parts.next())?;
+
+        // Seconds are ignored, but they must be valid.
+        if let Some(seconds) = parts.next() {
+            if number(Some(seconds))? >= 60 {
+                return Err(invalid());
+            }
+        }
+
+        if parts.next().is_some() {
+            return Err(invalid());
         }
+
+        Self::new(hours, minutes).map_err(|_| invalid())
+    }
+}
+
+impl fmt::Display for Time {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}:{:02}", self.hours(), self.minutes())
+    }
+}
+
+impl Serialize for Time {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.collect_str(self)
     }
 }
 
@@ -114,29 +268,89 @@ impl<'de> de::Visitor<'de> for DeserializeTime {
     type Value = Time;
 
     fn expecting(&self, formatter: &mut fmt::Formatter) -> fmt::Result {
-        formatter.write_str("u32 or string")
+        formatter.write_str("minutes since midnight or a time string")
     }
 
-    fn visit_u32<E>(self, value: u32) -> Result<Self::Value, E>
+    fn visit_u64<E>(self, value: u64) -> Result<Self::Value, E>
     where
         E: de::Error,
     {
-        Ok(Time::from(value))
+        let value = u32::try_from(value).map_err(E::custom)?;
+        Time::from_minutes(value).map_err(E::custom)
+    }
+
+    fn visit_i64<E>(self, value: i64) -> Result<Self::Value, E>
+    where
+        E: de::Error,
+    {
+        let value = u32::try_from(value).map_err(E::custom)?;
+        Time::from_minutes(value).map_err(E::custom)
     }
 
     fn visit_str<E>(self, value: &str) -> Result<Self::Value, E>
     where
         E: de::Error,
     {
-        Ok(Time::from(value))
+        value.parse().map_err(E::custom)
     }
 }
 
-fn deserialize_time<'de, D>(deserializer: D) -> Result<Time, D::Error>
-where
-    D: Deserializer<'de>,
-{
-    deserializer.deserialize_any(DeserializeTime)
+impl<'de> Deserialize<'de> for Time {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        deserializer.deserialize_any(DeserializeTime)
+    }
+}
+
+/// A span of time on a day. The end is exclusive, so back-to-back spans don't overlap.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash, Serialize, Deserialize)]
+pub struct TimeSpan {
+    pub start: Time,
+    pub end: Time,
+}
+
+impl TimeSpan {
+    /// Fails if `end` is before `start`.
+    pub fn new(start: Time, end: Time) -> anyhow::Result<Self> {
+        if end < start {
+            anyhow::bail!(
+                "The time span ends at {} before it starts at {}.",
+                end,
+                start
+            );
+        }
+
+        Ok(Self { start, end })
+    }
+
+    pub fn duration(&self) -> Duration {
+        self.end - self.start
+    }
+
+    /// Whether the time is within the span. The end isn't.
+    pub fn contains(&self, time: Time) -> bool {
+        self.start <= time && time < self.end
+    }
+
+    pub fn overlaps(&self, other: &TimeSpan) -> bool {
+        self.start < other.end && other.start < self.end
+    }
+
+    /// The part of the span that is also in the other one.
+    pub fn intersection(&self, other: &TimeSpan) -> Option<TimeSpan> {
+        match self.overlaps(other) {
+            true => Some(TimeSpan {
+                start: self.start.max(other.start),
+                end: self.end.min(other.end),
+            }),
+            false => None,
+        }
+    }
+}
+
+impl fmt::Display for TimeSpan {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}-{}", self.start, self.end)
+    }
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -159,6 +373,65 @@ pub struct Group {
     short_caption: String,
 
     teachers: Vec<Teacher>,
+
+    #[cfg(feature = "unstable-week-schedule")]
+    #[serde(default)]
+    substitutes: Vec<Teacher>,
+
+    #[cfg(feature = "unstable-week-schedule")]
+    #[serde(default)]
+    original_rooms: Vec<Room>,
+}
+
+impl Group {
+    /// The caption of the group.
+    pub fn caption(&self) -> &String {
+        &self.caption
+    }
+
+    /// The classes the students of the group are from.
+    pub fn class(&self) -> &String {
+        &self.class
+    }
+
+    /// The ID of the course the group belongs to.
+    pub fn course_id(&self) -> u32 {
+        self.course_id
+    }
+
+    pub fn full_caption(&self) -> &String {
+        &self.full_caption
+    }
+
+    pub fn id(&self) -> u32 {
+        self.id
+    }
+
+    /// The rooms the group has lessons in.
+    pub fn rooms(&self) -> &Vec<Room> {
+        &self.rooms
+    }
+
+    pub fn short_caption(&self) -> &String {
+        &self.short_caption
+    }
+
+    /// The teachers of the group.
+    pub fn teachers(&self) -> &Vec<Teacher> {
+        &self.teachers
+    }
+
+    /// The teachers substituting for the regular ones. Only set in the schedule of a week.
+    #[cfg(feature = "unstable-week-schedule")]
+    pub fn substitutes(&self) -> &Vec<Teacher> {
+        &self.substitutes
+    }
+
+    /// The regular rooms of the group, if the lesson was moved to `Group::rooms` for the week.
+    #[cfg(feature = "unstable-week-schedule")]
+    pub fn original_rooms(&self) -> &Vec<Room> {
+        &self.original_rooms
+    }
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -214,7 +487,6 @@ pub struct Reservation {
     pub color: Option<String>,
 
     /// The time when the reservation ends.
-    #[serde(deserialize_with = "deserialize_time")]
     pub end: Time,
 
     /// Groups that are participating in the reservation.
@@ -229,6 +501,119 @@ pub struct Reservation {
     pub reservation_id: u32,
 
     /// The time when the reservation starts.
-    #[serde(deserialize_with = "deserialize_time")]
     pub start: Time,
+
+    /// Whether the lesson is cancelled. Only set in the schedule of a week.
+    #[cfg(feature = "unstable-week-schedule")]
+    #[serde(default)]
+    pub cancelled: bool,
+}
+
+impl Reservation {
+    /// The time the reservation takes on its weekday. A reservation that ends before it starts
+    /// is treated as empty.
+    pub fn span(&self) -> TimeSpan {
+        TimeSpan {
+            start: self.start,
+            end: self.end.max(self.start),
+        }
+    }
+
+    /// Whether the reservation is on the same weekday as the other one and their times overlap.
+    pub fn overlaps(&self, other: &Reservation) -> bool {
+        self.weekday == other.weekday && self.span().overlaps(&other.span())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use proptest::prelude::*;
+
+    fn time(data: &str) -> Time {
+        data.parse().unwrap()
+    }
+
+    #[test]
+    fn parses_every_time_format() {
+        assert_eq!(time("13:50"), Time::new(13, 50).unwrap());
+        assert_eq!(time("8.05"), Time::new(8, 5).unwrap());
+        assert_eq!(time(" 08:05:00 "), Time::new(8, 5).unwrap());
+        assert_eq!(time("495"), Time::new(8, 15).unwrap());
+        assert_eq!(time("24:00"), Time::END_OF_DAY);
+
+        let json: Vec<Time> = serde_json::from_str(r#"[495, "8:15", "08.15"]"#).unwrap();
+        assert!(json.iter().all(|t| *t == Time::new(8, 15).unwrap()));
+
+        for invalid in [
+            "", "25:00", "12:60", "12:", ":30", "-1:00", "12:00:99", "1:2:3:4",
+        ] {
+            assert!(invalid.parse::<Time>().is_err(), "{:?}", invalid);
+        }
+        assert!(serde_json::from_str::<Time>("1441").is_err());
+        assert!(serde_json::from_str::<Time>("-5").is_err());
+    }
+
+    #[test]
+    fn does_arithmetic_without_leaving_the_day() {
+        let start = time("8:15");
+        let end = start + Duration::minutes(75);
+
+        assert_eq!(end, time("9:30"));
+        assert_eq!(end - start, Duration::minutes(75));
+        assert_eq!(start - end, Duration::minutes(-75));
+        assert!(start < end);
+
+        assert_eq!(time("23:30").checked_add(Duration::hours(1)), None);
+        assert_eq!(time("23:30") + Duration::hours(1), Time::END_OF_DAY);
+        assert_eq!(time("0:30") - Duration::hours(1), Time::MIDNIGHT);
+        assert_eq!(time("0:30") + Duration::minutes(-45), Time::MIDNIGHT);
+    }
+
+    #[test]
+    fn spans_overlap() {
+        let first = TimeSpan::new(time("8:15"), time("9:30")).unwrap();
+        let second = TimeSpan::new(time("9:30"), time("10:45")).unwrap();
+        let long = TimeSpan::new(time("9:00"), time("12:00")).unwrap();
+
+        assert!(!first.overlaps(&second));
+        assert!(first.overlaps(&long));
+        assert_eq!(
+            first.intersection(&long),
+            Some(TimeSpan::new(time("9:00"), time("9:30")).unwrap())
+        );
+        assert!(TimeSpan::new(time("10:00"), time("9:00")).is_err());
+    }
+
+    #[test]
+    fn converts_weekdays() {
+        assert_eq!(Weekday::try_from(6).unwrap(), Weekday::Saturday);
+        assert_eq!(Weekday::try_from(7).unwrap().number(), 7);
+        assert!(Weekday::try_from(0).is_err());
+        assert!(Weekday::try_from(8).is_err());
+        assert!(serde_json::from_str::<Weekday>("9").is_err());
+
+        let json = serde_json::to_string(&Weekday::Sunday).unwrap();
+        assert_eq!(json, "7");
+        assert_eq!(
+            serde_json::from_str::<Weekday>(&json).unwrap(),
+            Weekday::Sunday
+        );
+
+        assert_eq!(Weekday::from(chrono::Weekday::Sun), Weekday::Sunday);
+        assert_eq!(chrono::Weekday::from(Weekday::Monday), chrono::Weekday::Mon);
+    }
+
+    proptest! {
+        #[test]
+        fn parsing_never_panics(data in "\\PC{0,12}") {
+            let _ = data.parse::<Time>();
+        }
+
+        #[test]
+        fn display_round_trips(minutes in 0..=MINUTES_PER_DAY) {
+            let time = Time::from_minutes(minutes).unwrap();
+            prop_assert_eq!(time.to_string().parse::<Time>().unwrap(), time);
+        }
+    }
 }
@@ -1,3 +1,4 @@
+use chrono::NaiveDate;
 use serde::{Deserialize, Serialize};
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -7,3 +8,28 @@ pub struct Term {
     name: String,
     start_date: String,
 }
+
+impl Term {
+    /// The name of the term, for example "Syyslukukausi".
+    pub fn name(&self) -> &String {
+        &self.name
+    }
+
+    /// The first day of the term. Will be none if Wilma sent a date in an unknown format.
+    pub fn start_date(&self) -> Option<NaiveDate> {
+        NaiveDate::parse_from_str(&self.start_date, "%Y-%m-%d").ok()
+    }
+
+    /// The last day of the term. Will be none if Wilma sent a date in an unknown format.
+    pub fn end_date(&self) -> Option<NaiveDate> {
+        NaiveDate::parse_from_str(&self.end_date, "%Y-%m-%d").ok()
+    }
+
+    /// Whether the given date is within the term.
+    pub fn contains(&self, date: NaiveDate) -> bool {
+        match (self.start_date(), self.end_date()) {
+            (Some(start), Some(end)) => start <= date && date <= end,
+            _ => false,
+        }
+    }
+}